@@ -0,0 +1,315 @@
+use anyhow::{Context, Result};
+use azalea::blocks::{BlockState, BlockTrait};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A block read from the world
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Namespaced id, e.g. `minecraft:redstone_wire`
+    pub block_id: String,
+    pub properties: HashMap<String, String>,
+}
+
+impl fmt::Display for BlockInfo {
+    /// Formats as a block string, with properties sorted by name
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.block_id)?;
+        if !self.properties.is_empty() {
+            let mut properties: Vec<_> = self.properties.iter().collect();
+            properties.sort();
+            let states: Vec<String> = properties
+                .into_iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            write!(f, "[{}]", states.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a block read from the world matches a spec block string like `lever[powered=true]`
+/// The ids must be equal and every listed state must have the given value; states
+/// not listed are ignored. A bare id is taken to be in the `minecraft` namespace.
+/// Pure, so it can be checked against fabricated `BlockInfo`s without a server.
+pub fn blocks_match(expected: &str, actual: &BlockInfo) -> bool {
+    blocks_match_ignoring(expected, actual, &[])
+}
+
+/// Like `blocks_match`, but states named in `ignore` are not compared even when listed
+pub fn blocks_match_ignoring(expected: &str, actual: &BlockInfo, ignore: &[String]) -> bool {
+    let Ok((id, states)) = parse_block(expected) else {
+        return false;
+    };
+    id_matches(id, actual)
+        && states
+            .iter()
+            .filter(|(key, _)| !ignore.iter().any(|ignored| ignored == key))
+            .all(|(key, value)| actual.properties.get(*key).map(String::as_str) == Some(*value))
+}
+
+/// Compared states that differ, as `key (expected x, actual y)`
+/// Empty when the ids differ, since the states of different blocks are not comparable.
+pub fn state_mismatches(expected: &str, actual: &BlockInfo, ignore: &[String]) -> Vec<String> {
+    let Ok((id, states)) = parse_block(expected) else {
+        return Vec::new();
+    };
+    if !id_matches(id, actual) {
+        return Vec::new();
+    }
+    states
+        .into_iter()
+        .filter(|(key, _)| !ignore.iter().any(|ignored| ignored == key))
+        .filter_map(|(key, value)| {
+            let actual_value = actual.properties.get(key).map(String::as_str);
+            (actual_value != Some(value)).then(|| {
+                format!(
+                    "{} (expected {}, actual {})",
+                    key,
+                    value,
+                    actual_value.unwrap_or("<no such state>")
+                )
+            })
+        })
+        .collect()
+}
+
+fn id_matches(id: &str, actual: &BlockInfo) -> bool {
+    if id.contains(':') {
+        actual.block_id == id
+    } else {
+        actual.block_id.strip_prefix("minecraft:") == Some(id)
+    }
+}
+
+/// Whether a block is any kind of air (`air`, `cave_air` or `void_air`)
+pub fn is_air(block: &BlockInfo) -> bool {
+    matches!(
+        block.block_id.as_str(),
+        "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"
+    )
+}
+
+/// Blocks that always hold a water source without a `waterlogged` state
+const ALWAYS_WATERLOGGED: &[&str] = &[
+    "minecraft:bubble_column",
+    "minecraft:kelp",
+    "minecraft:kelp_plant",
+    "minecraft:seagrass",
+    "minecraft:tall_seagrass",
+];
+
+/// Fluid in a block as `water`, `lava` or `empty`, with the fluid's `level` state
+/// Level 0 is a source, 1-7 flow away from it and 8 and above are falling. Waterlogged
+/// blocks hold a water source.
+pub fn fluid_of(block: &BlockInfo) -> (&'static str, u8) {
+    let level = || {
+        block
+            .properties
+            .get("level")
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(0)
+    };
+    match block.block_id.as_str() {
+        "minecraft:water" => ("water", level()),
+        "minecraft:lava" => ("lava", level()),
+        id if ALWAYS_WATERLOGGED.contains(&id)
+            || block.properties.get("waterlogged").map(String::as_str) == Some("true") =>
+        {
+            ("water", 0)
+        }
+        _ => ("empty", 0),
+    }
+}
+
+/// Block id with the `minecraft` namespace filled in when it has none
+pub fn namespaced(id: &str) -> String {
+    if id.contains(':') {
+        id.to_string()
+    } else {
+        format!("minecraft:{}", id)
+    }
+}
+
+/// Swap the id of a spec block string for its alias, keeping the states and NBT
+/// `aliases` maps namespaced ids to the ids to expect instead, e.g. `minecraft:grass`
+/// to `minecraft:short_grass` on servers where the block was renamed.
+pub fn apply_alias<'a>(block: &'a str, aliases: &HashMap<String, String>) -> Cow<'a, str> {
+    if aliases.is_empty() {
+        return Cow::Borrowed(block);
+    }
+    let id_end = block.find(['[', '{']).unwrap_or(block.len());
+    match aliases.get(&namespaced(block[..id_end].trim())) {
+        Some(alias) => Cow::Owned(format!("{}{}", alias, &block[id_end..])),
+        None => Cow::Borrowed(block),
+    }
+}
+
+/// A regex replacement applied to every block string a placement or fill sends
+#[derive(Debug, Clone)]
+pub struct BlockTransform {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl BlockTransform {
+    /// `replacement` may refer to capture groups as `$1` or `${name}`
+    pub fn new(pattern: &str, replacement: &str) -> Result<Self> {
+        Ok(Self {
+            pattern: Regex::new(pattern)
+                .with_context(|| format!("Invalid block transform pattern '{}'", pattern))?,
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+/// Apply every transform to a block string in order, each to the previous one's result
+pub fn transform_block<'a>(block: &'a str, transforms: &[BlockTransform]) -> Cow<'a, str> {
+    let mut block = Cow::Borrowed(block);
+    for transform in transforms {
+        let replaced = match transform
+            .pattern
+            .replace_all(&block, transform.replacement.as_str())
+        {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(replaced) => Some(replaced),
+        };
+        if let Some(replaced) = replaced {
+            block = Cow::Owned(replaced);
+        }
+    }
+    block
+}
+
+/// Split a block string into its id and `key=value` states, ignoring block entity NBT
+pub fn parse_block(block: &str) -> Result<(&str, Vec<(&str, &str)>)> {
+    let without_nbt = block.split('{').next().unwrap_or(block).trim();
+
+    let (id, states) = match without_nbt.split_once('[') {
+        Some((id, rest)) => match rest.strip_suffix(']') {
+            Some(states) => (id, states),
+            None => anyhow::bail!("Unclosed '[' in block '{}'", block),
+        },
+        None => (without_nbt, ""),
+    };
+
+    let mut pairs = Vec::new();
+    for pair in states.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let Some((key, value)) = pair.split_once('=') else {
+            anyhow::bail!("Malformed state '{}' in block '{}'", pair, block);
+        };
+        pairs.push((key.trim(), value.trim()));
+    }
+    Ok((id.trim(), pairs))
+}
+
+/// Validate a block string like `minecraft:repeater[delay=4,facing=east]` before it is sent
+/// Checks the block id against azalea's registry and every state key against that
+/// block's properties. Blocks from other namespaces (datapacks, mods) are passed through.
+pub fn validate_block(block: &str) -> Result<()> {
+    // Block entity NBT is not validated
+    let (id, states) = parse_block(block)?;
+
+    let name = match id.split_once(':') {
+        Some(("minecraft", name)) => name,
+        Some(_) => return Ok(()),
+        None => id,
+    };
+
+    let Ok(registry_block) = azalea::registry::Block::from_str(&format!("minecraft:{}", name))
+    else {
+        anyhow::bail!("Unknown block id '{}' in '{}'", id, block);
+    };
+
+    if !states.is_empty() {
+        let properties =
+            Box::<dyn BlockTrait>::from(BlockState::from(registry_block)).property_map();
+        for (key, _) in states {
+            if !properties.contains_key(key) {
+                anyhow::bail!("Unknown state '{}' for {} in '{}'", key, id, block);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: &str, properties: &[(&str, &str)]) -> BlockInfo {
+        BlockInfo {
+            block_id: id.to_string(),
+            properties: properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn namespace_is_optional() {
+        let stone = block("minecraft:stone", &[]);
+        assert!(blocks_match("minecraft:stone", &stone));
+        assert!(blocks_match("stone", &stone));
+        assert!(!blocks_match("othermod:stone", &stone));
+        assert!(!blocks_match(
+            "minecraft:stone",
+            &block("othermod:stone", &[])
+        ));
+    }
+
+    #[test]
+    fn underscore_ids_match_whole() {
+        let wire = block("minecraft:redstone_wire", &[]);
+        assert!(blocks_match("redstone_wire", &wire));
+        assert!(blocks_match("minecraft:redstone_wire", &wire));
+        assert!(!blocks_match(
+            "redstone_wire",
+            &block("minecraft:redstone_wire_extra", &[])
+        ));
+        assert!(!blocks_match("wire", &wire));
+    }
+
+    #[test]
+    fn redstone_matches_neither_wire_nor_stone() {
+        assert!(!blocks_match(
+            "redstone",
+            &block("minecraft:redstone_wire", &[])
+        ));
+        assert!(!blocks_match("redstone", &block("minecraft:stone", &[])));
+        assert!(!blocks_match(
+            "stone",
+            &block("minecraft:redstone_block", &[])
+        ));
+        assert!(!blocks_match(
+            "minecraft:stone",
+            &block("minecraft:redstone_wire", &[])
+        ));
+    }
+
+    #[test]
+    fn listed_states_are_a_subset() {
+        let lever = block(
+            "minecraft:lever",
+            &[("powered", "true"), ("facing", "north"), ("face", "wall")],
+        );
+        assert!(blocks_match("lever", &lever));
+        assert!(blocks_match("lever[powered=true]", &lever));
+        assert!(blocks_match(
+            "minecraft:lever[facing=north, powered=true]",
+            &lever
+        ));
+        assert!(!blocks_match("lever[powered=false]", &lever));
+        assert!(!blocks_match("lever[powered=true,attached=true]", &lever));
+        assert!(blocks_match_ignoring(
+            "lever[powered=false,facing=north]",
+            &lever,
+            &["powered".to_string()]
+        ));
+    }
+}
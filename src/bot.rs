@@ -1,9 +1,17 @@
-use anyhow::Result;
+use crate::blocks::BlockInfo;
+use anyhow::{Context, Result};
+use azalea::blocks::{BlockState, BlockTrait};
+use azalea::core::position::ChunkPos;
 use azalea::prelude::*;
-use parking_lot::RwLock;
+use azalea::world::ChunkStorage;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::io::{LineWriter, Write};
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 
 #[derive(Clone, Component)]
 struct State {
@@ -22,11 +30,80 @@ impl Default for State {
     }
 }
 
+/// Offline-mode username used when none is configured
+pub const DEFAULT_USERNAME: &str = "FlintMC_TestBot";
+
+/// How long `connect` waits for the client to initialize by default
+pub const DEFAULT_INIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `connect` waits for an initialized client to enter the game by default
+pub const DEFAULT_JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause between checks while `connect` waits
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Longest username the game accepts
+const MAX_USERNAME_LEN: usize = 16;
+
+/// Commands that can wait in the rate-limited queue before `send_command` waits for room
+const COMMAND_QUEUE_CAPACITY: usize = 256;
+
+/// A queued command, with where to report the result once it has been sent
+type QueuedCommand = (String, Option<oneshot::Sender<Result<()>>>);
+
+/// What the server reported about itself in its status response
+#[derive(Debug, Clone)]
+pub struct ServerInfo {
+    /// Version name; servers other than vanilla usually prefix their brand, e.g. `Paper 1.21.4`
+    pub version: String,
+    pub protocol: i32,
+}
+
+impl ServerInfo {
+    /// Protocol version the bot speaks
+    pub fn bot_protocol() -> i32 {
+        azalea::protocol::packets::PROTOCOL_VERSION
+    }
+}
+
+/// File that every sent command is appended to, under a comment naming the tick and
+/// test it was sent for, so a run can be replayed by hand
+struct CommandTrace {
+    out: LineWriter<std::fs::File>,
+    /// Tick and test the next commands are sent for; `None` tick is setup or cleanup
+    context: (Option<u32>, Option<String>),
+    /// Context of the last header written
+    written: Option<(Option<u32>, Option<String>)>,
+}
+
+impl CommandTrace {
+    fn record(&mut self, command: &str) -> std::io::Result<()> {
+        if self.written.as_ref() != Some(&self.context) {
+            match &self.context {
+                (Some(tick), Some(test)) => writeln!(self.out, "# tick {} [{}]", tick, test)?,
+                (Some(tick), None) => writeln!(self.out, "# tick {}", tick)?,
+                (None, _) => writeln!(self.out, "# setup and cleanup")?,
+            }
+            self.written = Some(self.context.clone());
+        }
+        writeln!(self.out, "{}", command.trim_start_matches('/'))
+    }
+}
+
 #[derive(Default)]
 pub struct TestBot {
     client: Option<Arc<RwLock<Option<Client>>>>,
     in_game: Option<Arc<AtomicBool>>,
     chat_rx: Option<mpsc::UnboundedReceiver<String>>,
+    server: Option<String>,
+    username: Option<String>,
+    server_info: Option<ServerInfo>,
+    command_rate: Option<u32>,
+    return_when_queued: bool,
+    command_queue: Option<mpsc::Sender<QueuedCommand>>,
+    init_timeout: Option<Duration>,
+    join_timeout: Option<Duration>,
+    trace: Option<Mutex<CommandTrace>>,
 }
 
 impl TestBot {
@@ -34,11 +111,96 @@ impl TestBot {
         Self::default()
     }
 
+    pub fn set_username(&mut self, username: String) {
+        self.username = Some(username);
+    }
+
+    pub fn username(&self) -> &str {
+        self.username.as_deref().unwrap_or(DEFAULT_USERNAME)
+    }
+
+    /// Send at most `per_second` commands a second through a queue, so servers don't
+    /// kick the bot for spam; takes effect on the next connect
+    pub fn set_command_rate(&mut self, per_second: u32) {
+        self.command_rate = Some(per_second.max(1));
+    }
+
+    /// How long `connect` waits for the client to initialize [default: 5s]
+    pub fn set_init_timeout(&mut self, timeout: Duration) {
+        self.init_timeout = Some(timeout);
+    }
+
+    /// How long `connect` waits for the initialized client to enter the game [default: 10s]
+    pub fn set_join_timeout(&mut self, timeout: Duration) {
+        self.join_timeout = Some(timeout);
+    }
+
+    /// Append every command sent from now on to the file at `path`
+    pub fn set_trace(&mut self, path: &Path) -> Result<()> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open trace file {}", path.display()))?;
+        let mut out = LineWriter::new(file);
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        writeln!(out, "# flintmc trace, started at unix time {}", started)
+            .with_context(|| format!("Failed to write trace file {}", path.display()))?;
+        self.trace = Some(Mutex::new(CommandTrace {
+            out,
+            context: (None, None),
+            written: None,
+        }));
+        Ok(())
+    }
+
+    /// Tick and test that the next traced commands are sent for; no tick for setup
+    /// and cleanup, no test for commands shared by the whole tick
+    pub fn set_trace_context(&self, tick: Option<u32>, test: Option<&str>) {
+        if let Some(trace) = &self.trace {
+            trace.lock().context = (tick, test.map(str::to_string));
+        }
+    }
+
+    /// Have `send_command` return once a rate-limited command is queued rather than sent
+    pub fn set_return_when_queued(&mut self, enabled: bool) {
+        self.return_when_queued = enabled;
+    }
+
+    /// Append `_` and four random hex digits to the username, shortening it to stay
+    /// within 16 characters, so concurrent bots on one server get distinct names
+    pub fn add_random_suffix(&mut self) {
+        use std::hash::BuildHasher;
+        let suffix =
+            std::collections::hash_map::RandomState::new().hash_one(std::process::id()) % 0x10000;
+        let base: String = self.username().chars().take(MAX_USERNAME_LEN - 5).collect();
+        self.username = Some(format!("{}_{:04x}", base, suffix));
+    }
+
     pub async fn connect(&mut self, server: &str) -> Result<()> {
-        let account = Account::offline("FlintMC_TestBot");
+        let account = Account::offline(self.username());
 
         tracing::info!("Connecting to server: {}", server);
 
+        // The status ping reports the server version, which the game connection doesn't
+        match azalea::ping::ping_server(server).await {
+            Ok(status) => {
+                tracing::info!(
+                    "Server version {} (protocol {})",
+                    status.version.name,
+                    status.version.protocol
+                );
+                self.server_info = Some(ServerInfo {
+                    version: status.version.name,
+                    protocol: status.version.protocol,
+                });
+            }
+            Err(e) => tracing::debug!("Status ping failed: {}", e),
+        }
+
         // Create chat channel
         let (chat_tx, chat_rx) = mpsc::unbounded_channel();
 
@@ -63,6 +225,12 @@ impl TestBot {
                         state.in_game.store(true, Ordering::SeqCst);
                         tracing::info!("Bot in game state");
                     }
+                    Event::Disconnect(reason) => {
+                        // Clear the handle so the executor can observe the lost connection
+                        state.in_game.store(false, Ordering::SeqCst);
+                        *state.client_handle.write() = None;
+                        tracing::warn!("Bot disconnected: {:?}", reason.map(|r| r.to_string()));
+                    }
                     Event::Chat(m) => {
                         // Extract the message content and send it through the channel
                         let message = m.message().to_string();
@@ -87,41 +255,115 @@ impl TestBot {
         });
 
         // Wait for client to initialize
-        for _ in 0..50 {
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            if client_handle.read().is_some() {
-                break;
-            }
+        let init_timeout = self.init_timeout.unwrap_or(DEFAULT_INIT_TIMEOUT);
+        let start = tokio::time::Instant::now();
+        while client_handle.read().is_none() && start.elapsed() < init_timeout {
+            tokio::time::sleep(CONNECT_POLL_INTERVAL).await;
         }
 
         if client_handle.read().is_none() {
-            anyhow::bail!("Failed to initialize bot connection");
+            anyhow::bail!(
+                "Bot connection never initialized within {}s; the server is unreachable or did not accept the connection (raise the init timeout for a slow server)",
+                init_timeout.as_secs_f64()
+            );
         }
 
         // Wait for bot to be in game state
         tracing::info!("Waiting for bot to enter game state...");
-        for _ in 0..100 {
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            if in_game.load(Ordering::SeqCst) {
-                break;
-            }
+        let join_timeout = self.join_timeout.unwrap_or(DEFAULT_JOIN_TIMEOUT);
+        let start = tokio::time::Instant::now();
+        while !in_game.load(Ordering::SeqCst) && start.elapsed() < join_timeout {
+            tokio::time::sleep(CONNECT_POLL_INTERVAL).await;
         }
 
         if !in_game.load(Ordering::SeqCst) {
-            anyhow::bail!("Bot failed to enter game state within timeout");
+            anyhow::bail!(
+                "Bot connected but never entered the game within {}s; the server is still logging it in or loading the world (raise the join timeout for a slow server)",
+                join_timeout.as_secs_f64()
+            );
         }
 
+        // Replacing the sender ends the previous connection's queue once it drains
+        self.command_queue = self
+            .command_rate
+            .map(|per_second| spawn_command_queue(client_handle.clone(), per_second));
         self.client = Some(client_handle);
         self.in_game = Some(in_game);
         self.chat_rx = Some(chat_rx);
+        self.server = Some(server.to_string());
         tracing::info!("Connected successfully and in game state");
 
-        // Give a small amount of extra time for world data to sync
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        Ok(())
+    }
+
+    /// Server version from the last status ping, if the server answered it
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
 
+    /// Poll until the chunk containing `pos` is present in the bot's chunk storage
+    pub async fn wait_for_chunk(&self, pos: [i32; 3], timeout: std::time::Duration) -> Result<()> {
+        let chunk_pos = ChunkPos::from(azalea::BlockPos::new(pos[0], pos[1], pos[2]));
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !self.chunk_loaded(chunk_pos)? {
+            if tokio::time::Instant::now() >= deadline {
+                anyhow::bail!(
+                    "Chunk [{}, {}] containing [{}, {}, {}] was not loaded within {}ms",
+                    chunk_pos.x,
+                    chunk_pos.z,
+                    pos[0],
+                    pos[1],
+                    pos[2],
+                    timeout.as_millis()
+                );
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        }
+        tracing::debug!("Chunk [{}, {}] loaded", chunk_pos.x, chunk_pos.z);
         Ok(())
     }
 
+    fn chunk_loaded(&self, chunk_pos: ChunkPos) -> Result<bool> {
+        self.with_chunks(|chunks| chunks.get(&chunk_pos).is_some())
+    }
+
+    /// Run `read` on the chunks the client has loaded, under a single world lock
+    fn with_chunks<T>(&self, read: impl FnOnce(&ChunkStorage) -> T) -> Result<T> {
+        if let Some(client_handle) = &self.client {
+            if let Some(client) = client_handle.read().as_ref() {
+                let world_lock = client.world();
+                let world = world_lock.read();
+                Ok(read(&world.chunks))
+            } else {
+                anyhow::bail!("Bot not initialized")
+            }
+        } else {
+            anyhow::bail!("Bot not connected")
+        }
+    }
+
+    /// Whether the bot is still in game on the server it connected to
+    pub fn is_connected(&self) -> bool {
+        let in_game = self
+            .in_game
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::SeqCst));
+        let has_client = self
+            .client
+            .as_ref()
+            .is_some_and(|handle| handle.read().is_some());
+        in_game && has_client
+    }
+
+    /// Connect again to the server from the last successful `connect`
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let Some(server) = self.server.clone() else {
+            anyhow::bail!("Bot was never connected");
+        };
+        tracing::info!("Reconnecting to server: {}", server);
+        self.connect(&server).await
+    }
+
     /// Wait for a chat message with timeout
     pub async fn recv_chat_timeout(&mut self, timeout: std::time::Duration) -> Option<String> {
         if let Some(ref mut rx) = self.chat_rx {
@@ -134,18 +376,29 @@ impl TestBot {
         }
     }
 
+    /// Send a command, through the rate-limited queue if a command rate is set, and
+    /// append it to the trace file once sent
     pub async fn send_command(&self, command: &str) -> Result<()> {
+        self.dispatch_command(command).await?;
+        if let Some(trace) = &self.trace
+            && let Err(e) = trace.lock().record(command)
+        {
+            tracing::warn!("Failed to write command trace: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Send or queue a command without tracing it
+    async fn dispatch_command(&self, command: &str) -> Result<()> {
+        if let Some(queue) = &self.command_queue {
+            return self.enqueue_command(queue, command).await;
+        }
         if let Some(client_handle) = &self.client {
             if let Some(client) = client_handle.read().as_ref() {
-                // Add "/" prefix if not present
-                let command_with_slash = if command.starts_with('/') {
-                    command.to_string()
-                } else {
-                    format!("/{}", command)
-                };
-                tracing::debug!("Sending command: {}", command_with_slash);
-                client.chat(&command_with_slash);
+                chat_command(client, command);
                 Ok(())
+            } else if self.server.is_some() {
+                anyhow::bail!("Lost connection to server")
             } else {
                 anyhow::bail!("Bot not initialized")
             }
@@ -154,61 +407,205 @@ impl TestBot {
         }
     }
 
-    pub async fn get_block(&self, pos: [i32; 3]) -> Result<Option<String>> {
-        if let Some(client_handle) = &self.client {
-            if let Some(client) = client_handle.read().as_ref() {
-                let block_pos = azalea::BlockPos::new(pos[0], pos[1], pos[2]);
-                let world_lock = client.world();
-                let world = world_lock.read();
-                let block_state = world.get_block_state(block_pos);
-
-                if let Some(state) = block_state {
-                    // Return block state as debug string
-                    let state_str = format!("{:?}", state);
-                    Ok(Some(state_str))
-                } else {
-                    Ok(None)
-                }
-            } else {
-                anyhow::bail!("Bot not initialized")
-            }
-        } else {
-            anyhow::bail!("Bot not connected")
+    /// Queue a command, then wait for it to be sent unless `return_when_queued` is set
+    /// Waits for room while the queue is full.
+    async fn enqueue_command(
+        &self,
+        queue: &mpsc::Sender<QueuedCommand>,
+        command: &str,
+    ) -> Result<()> {
+        if !self.is_connected() {
+            anyhow::bail!("Lost connection to server");
+        }
+        if self.return_when_queued {
+            queue
+                .send((command.to_string(), None))
+                .await
+                .map_err(|_| anyhow::anyhow!("Command queue closed"))?;
+            return Ok(());
+        }
+
+        let (sent_tx, sent_rx) = oneshot::channel();
+        queue
+            .send((command.to_string(), Some(sent_tx)))
+            .await
+            .map_err(|_| anyhow::anyhow!("Command queue closed"))?;
+        sent_rx
+            .await
+            .map_err(|_| anyhow::anyhow!("Command queue closed before /{} was sent", command))?
+    }
+
+    /// Read the block at a position; air in a loaded chunk is a block like any other
+    /// Fails if the chunk is not loaded (or the position is outside the world's height),
+    /// since nothing can be said about the block then. Uses the same lookup as
+    /// `snapshot_region`, so single blocks and snapshots always agree.
+    pub async fn get_block(&self, pos: [i32; 3]) -> Result<BlockInfo> {
+        match self.with_chunks(|chunks| read_block(chunks, pos))? {
+            Some(block) => Ok(block),
+            None => anyhow::bail!(
+                "Chunk at [{}, {}, {}] is not loaded (or the position is outside the world's height), so the block can't be read; did the bot teleport near the test? (--no-teleport keeps it where it is)",
+                pos[0],
+                pos[1],
+                pos[2]
+            ),
         }
     }
 
-    pub async fn get_block_state_property(
+    /// Read every block in the box from `min` to `max` (inclusive) under a single world lock
+    /// Positions in unloaded chunks are missing from the map. Region assertions use this
+    /// instead of a lock and an await per block.
+    pub fn snapshot_region(
+        &self,
+        min: [i32; 3],
+        max: [i32; 3],
+    ) -> Result<HashMap<[i32; 3], BlockInfo>> {
+        self.with_chunks(|chunks| read_region(chunks, min, max))
+    }
+
+    /// Read the namespaced block id at a position, e.g. `minecraft:redstone_wire`
+    pub async fn get_block_id(&self, pos: [i32; 3]) -> Result<String> {
+        Ok(self.get_block(pos).await?.block_id)
+    }
+
+    /// Read a single block state property value at a position, e.g. `true` for `powered`
+    pub async fn get_block_property(
         &self,
         pos: [i32; 3],
         property: &str,
     ) -> Result<Option<String>> {
-        if let Some(client_handle) = &self.client {
-            if let Some(client) = client_handle.read().as_ref() {
-                let block_pos = azalea::BlockPos::new(pos[0], pos[1], pos[2]);
-                let world_lock = client.world();
-                let world = world_lock.read();
-                let block_state = world.get_block_state(block_pos);
-
-                if let Some(state) = block_state {
-                    // For now, return the full state string representation
-                    // The property API has changed in newer versions
-                    let state_str = format!("{:?}", state);
-
-                    // Simple string matching for common properties
-                    if state_str.contains(&format!("{}: ", property)) {
-                        // Try to extract the value
-                        Ok(Some(state_str))
-                    } else {
-                        Ok(None)
+        Ok(self.get_block(pos).await?.properties.remove(property))
+    }
+}
+
+/// Send a command as chat, adding the leading `/` if it is missing
+fn chat_command(client: &Client, command: &str) {
+    let command_with_slash = if command.starts_with('/') {
+        command.to_string()
+    } else {
+        format!("/{}", command)
+    };
+    tracing::debug!("Sending command: {}", command_with_slash);
+    client.chat(&command_with_slash);
+}
+
+/// Drain queued commands in order, at most `per_second` a second
+fn spawn_command_queue(
+    client_handle: Arc<RwLock<Option<Client>>>,
+    per_second: u32,
+) -> mpsc::Sender<QueuedCommand> {
+    let (tx, mut rx) = mpsc::channel::<QueuedCommand>(COMMAND_QUEUE_CAPACITY);
+    tokio::spawn(async move {
+        let mut pace = tokio::time::interval(std::time::Duration::from_secs_f64(
+            1.0 / f64::from(per_second),
+        ));
+        // After an idle spell, resume at the normal pace instead of sending a catch-up burst
+        pace.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        while let Some((command, sent)) = rx.recv().await {
+            pace.tick().await;
+            let result = match client_handle.read().as_ref() {
+                Some(client) => {
+                    chat_command(client, &command);
+                    Ok(())
+                }
+                None => Err(anyhow::anyhow!("Lost connection to server")),
+            };
+            match sent {
+                Some(sent) => {
+                    let _ = sent.send(result);
+                }
+                None => {
+                    if let Err(e) = result {
+                        tracing::warn!("Queued command /{} was not sent: {}", command, e);
                     }
-                } else {
-                    Ok(None)
                 }
-            } else {
-                anyhow::bail!("Bot not initialized")
             }
-        } else {
-            anyhow::bail!("Bot not connected")
         }
+    });
+    tx
+}
+
+/// The block at a position, or `None` if its chunk isn't loaded or it's outside the
+/// world's height
+fn read_block(chunks: &ChunkStorage, pos: [i32; 3]) -> Option<BlockInfo> {
+    chunks
+        .get_block_state(azalea::BlockPos::new(pos[0], pos[1], pos[2]))
+        .map(block_info)
+}
+
+/// Every readable block in the box from `min` to `max` (inclusive), by position
+fn read_region(
+    chunks: &ChunkStorage,
+    min: [i32; 3],
+    max: [i32; 3],
+) -> HashMap<[i32; 3], BlockInfo> {
+    let mut blocks = HashMap::new();
+    for x in min[0]..=max[0] {
+        for y in min[1]..=max[1] {
+            for z in min[2]..=max[2] {
+                if let Some(block) = read_block(chunks, [x, y, z]) {
+                    blocks.insert([x, y, z], block);
+                }
+            }
+        }
+    }
+    blocks
+}
+
+fn block_info(state: BlockState) -> BlockInfo {
+    let block = Box::<dyn BlockTrait>::from(state);
+    BlockInfo {
+        block_id: format!("minecraft:{}", block.id()),
+        properties: block
+            .property_map()
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea::world::{Chunk, PartialChunkStorage};
+
+    /// Chunk storage with only chunk [0, 0] loaded, holding one stone block
+    fn loaded_chunk() -> ChunkStorage {
+        let mut chunks = ChunkStorage::default();
+        let mut partial = PartialChunkStorage::default();
+        partial.set(&ChunkPos::new(0, 0), Some(Chunk::default()), &mut chunks);
+        chunks.set_block_state(
+            azalea::BlockPos::new(14, 64, 2),
+            BlockState::from(azalea::registry::Block::Stone),
+        );
+        chunks
+    }
+
+    #[test]
+    fn region_snapshot_matches_single_reads() {
+        let chunks = loaded_chunk();
+        // Crosses from the loaded chunk into unloaded ones on X and Z
+        let (min, max) = ([13, 63, -1], [17, 65, 3]);
+        let snapshot = read_region(&chunks, min, max);
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    assert_eq!(
+                        snapshot.get(&[x, y, z]),
+                        read_block(&chunks, [x, y, z]).as_ref(),
+                        "[{}, {}, {}]",
+                        x,
+                        y,
+                        z
+                    );
+                }
+            }
+        }
+
+        // Only x 13..=15 and z 0..=3 lie in the loaded chunk
+        assert_eq!(snapshot.len(), 3 * 3 * 4);
+        assert_eq!(snapshot[&[14, 64, 2]].block_id, "minecraft:stone");
+        assert_eq!(snapshot[&[13, 63, 0]].block_id, "minecraft:air");
+        assert_eq!(read_block(&chunks, [16, 64, 2]), None);
+        assert_eq!(read_block(&chunks, [14, 64, -1]), None);
     }
 }
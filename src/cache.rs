@@ -0,0 +1,34 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Results cache in the working directory, written after every run
+pub const CACHE_FILE: &str = ".flint-cache.json";
+
+/// Outcome of the last run, read back by `--rerun-failed`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResultsCache {
+    pub failed: Vec<FailedTest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FailedTest {
+    pub name: String,
+    /// Canonical path of the spec file
+    pub file: PathBuf,
+}
+
+impl ResultsCache {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read results cache {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse results cache {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write results cache {}", path.display()))
+    }
+}
@@ -0,0 +1,78 @@
+use crate::executor::ServerFlavor;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Config file looked up in the working directory when `--config` is not given
+pub const DEFAULT_CONFIG_FILE: &str = "flint.toml";
+
+/// Settings shared by a team through `flint.toml`
+/// Every field is optional; CLI flags override the file and the file overrides
+/// the built-in defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Server address (e.g., localhost:25565)
+    pub server: Option<String>,
+    /// Server software, which decides how game time is frozen
+    pub server_flavor: Option<ServerFlavor>,
+    /// Offline-mode username for the bot
+    pub username: Option<String>,
+    /// Minimum wait in ms after advancing ticks before running assertions
+    pub tick_delay_ms: Option<u64>,
+    /// Pause in ms between the blocks of a `place_each`
+    pub place_delay_ms: Option<u64>,
+    /// World-editing entries sent per tick before pausing for the server to catch up
+    pub max_burst: Option<usize>,
+    /// Most commands the bot sends per second, queueing the rest; unlimited if unset
+    pub command_rate: Option<u32>,
+    /// Return from a rate-limited command once it is queued instead of once it is sent
+    pub return_when_queued: Option<bool>,
+    /// Seconds to wait for the bot's connection to initialize
+    pub init_timeout_secs: Option<u64>,
+    /// Seconds to wait for the connected bot to enter the game
+    pub join_timeout_secs: Option<u64>,
+    /// Maximum wait in ms for the chunks around the tests to load
+    pub world_load_timeout_ms: Option<u64>,
+    /// Commands run once after connecting; an empty list skips them
+    pub connect_commands: Option<Vec<String>>,
+    /// Commands run with `--seed` before every run, `{seed}` standing for the seed
+    pub seed_commands: Option<Vec<String>>,
+    /// Gap in blocks between automatically arranged test areas
+    pub spacing: Option<i32>,
+    /// Block ids to expect in place of others, for servers that renamed blocks
+    pub block_aliases: Option<HashMap<String, String>>,
+    /// Regex replacements applied in order to the blocks that placements and fills send
+    pub block_transforms: Option<Vec<BlockTransformRule>>,
+}
+
+/// One `[[block_transforms]]` entry of `flint.toml`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockTransformRule {
+    pub pattern: String,
+    /// Replacement text; `$1` or `${name}` insert capture groups
+    pub replace: String,
+}
+
+impl Config {
+    /// Load an explicit config file, or `flint.toml` from the working directory if present
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        let path = match explicit {
+            Some(path) => path,
+            None => {
+                let default = Path::new(DEFAULT_CONFIG_FILE);
+                if !default.exists() {
+                    return Ok(Self::default());
+                }
+                default
+            }
+        };
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config {}", path.display()))
+    }
+}
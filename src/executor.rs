@@ -1,14 +1,282 @@
-use crate::bot::TestBot;
+use crate::blocks::{
+    BlockInfo, BlockTransform, apply_alias, blocks_match_ignoring, fluid_of, is_air, namespaced,
+    state_mismatches, transform_block, validate_block,
+};
+use crate::bot::{ServerInfo, TestBot};
+use crate::layout;
+use crate::spec::{
+    CleanupFill, EntryAction, ExtAction, ExtEntry, Phase, SourceLocation, SpecExtras, action_phase,
+    action_positions, action_summary, direction_step, resolve_command,
+};
+use crate::suite::Suite;
 use anyhow::Result;
 use colored::Colorize;
 use flint_core::results::TestResult;
 use flint_core::test_spec::{ActionType, TestSpec, TimelineEntry};
 use flint_core::timeline::TimelineAggregate;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::io::{self, Write};
 
+/// Default minimum wait after advancing ticks before running assertions
+pub const DEFAULT_TICK_DELAY_MS: u64 = 200;
+
+/// Default pause between the blocks of a `place_each`
+pub const DEFAULT_PLACE_DELAY_MS: u64 = 10;
+
+/// Pause after each burst of world edits when `--max-burst` limits them, about one game tick
+const BURST_PAUSE_MS: u64 = 50;
+
+/// Default time to wait for the chunks around the first actions to load
+pub const DEFAULT_WORLD_LOAD_TIMEOUT_MS: u64 = 10_000;
+
+/// Commands run once after connecting, so the bot may use `/setblock` and friends
+pub const DEFAULT_CONNECT_COMMANDS: &[&str] = &["gamemode creative @s"];
+
+/// Commands run with `--seed` before every run; `{seed}` is replaced with the seed
+pub const DEFAULT_SEED_COMMANDS: &[&str] = &["random reset * {seed}"];
+
+/// Waits between reads while a block assertion settles within its tick, doubling from
+/// the first delay up to the maximum until the budget is spent
+const SETTLE_FIRST_DELAY_MS: u64 = 25;
+const SETTLE_MAX_DELAY_MS: u64 = 200;
+const SETTLE_BUDGET_MS: u64 = 500;
+
+/// Longest wait for the server to answer the setblock or fill commands of an entry
+const COMMAND_FEEDBACK_TIMEOUT_MS: u64 = 500;
+
+/// Feedback vanilla sends when a setblock, fill or clone went through (or changed nothing)
+/// A world command without one of these counts as unanswered, and a fully unanswered
+/// entry turns the rejection checks off for the session.
+const COMMAND_ACKNOWLEDGEMENTS: &[&str] = &[
+    "Changed the block",
+    "Could not set the block",
+    "Successfully filled",
+    "No blocks were filled",
+    "Successfully cloned",
+    "No blocks were cloned",
+];
+
+/// Vanilla's error feedback for a rejected setblock, fill or clone
+/// Whole phrases, so chat that merely contains a word like "Invalid" is not mistaken
+/// for an error.
+const COMMAND_REJECTIONS: &[&str] = &[
+    "Unknown block type",
+    "Unknown or incomplete command",
+    "Incorrect argument for command",
+    "Expected whitespace to end one argument",
+    "Expected value for property",
+    "Expected closing ] for block state properties",
+    "Invalid block state",
+    "does not have property",
+    "does not accept",
+    "That position is not loaded",
+    "That position is out of this world",
+    "Too many blocks in the specified area",
+    "<--[HERE]",
+];
+
+/// Blocks left empty between the test areas and their tick-0 copies
+const STASH_GAP: i32 = 4;
+
+/// Highest block y of an overworld with the default height
+const WORLD_TOP_Y: i32 = 319;
+
+/// Extra reads after a failed block assertion, to tell slow updates from wrong ones
+const LATE_CHECK_ATTEMPTS: u32 = 6;
+const LATE_CHECK_DELAY_MS: u64 = 50;
+
+/// Executed entries listed before the first failure of a test with `--verbose-failures`
+const FAILURE_CONTEXT_ENTRIES: usize = 5;
+
+/// Maximum number of mismatched positions listed in a region assertion failure
+const MAX_REPORTED_MISMATCHES: usize = 10;
+
+/// Per-test counters collected while the merged timeline runs
+#[derive(Debug, Default, Clone)]
+struct TestOutcome {
+    passed: usize,
+    /// Failed assertions
+    failed: usize,
+    /// Placements, fills, clones and commands that failed
+    setup_errors: usize,
+    /// Tick of the setup error that stopped the test, with `--abort-on-setup-error`
+    aborted_at: Option<u32>,
+    /// Entries skipped because their required assertion did not pass
+    skipped: usize,
+    /// Failure messages prefixed with their tick
+    failures: Vec<String>,
+    /// Outcome of every assertion with an `id`, for `requires` checks
+    assertions: HashMap<String, bool>,
+    /// Latest executed entries as "Tick N: summary", for `--verbose-failures`
+    history: VecDeque<String>,
+    /// Every counted assertion in the order it was checked
+    assertion_log: Vec<AssertionResult>,
+}
+
+/// An entry due at the current tick of the merged timeline
+enum TickEntry<'a> {
+    Core(&'a TimelineEntry),
+    Ext(&'a ExtEntry),
+    /// A failed assertion with `within` re-checked, with the tick it was scheduled for
+    Retry(&'a ExtEntry, u32),
+}
+
+/// Server software the bot talks to, which decides how game time is controlled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerFlavor {
+    /// Vanilla 1.20.3+ with `/tick freeze` and `/tick sprint`
+    #[default]
+    Vanilla,
+    /// Paper, which keeps the vanilla `/tick` command
+    Paper,
+    /// Fabric, which keeps the vanilla `/tick` command
+    Fabric,
+    /// Servers without `/tick`; time keeps running and ticks are waited out in real time
+    Legacy,
+}
+
+/// Pause between repetitions of an entry with `repeat`
+const REPEAT_DELAY_MS: u64 = 10;
+
+/// Game ticks advanced between checks of the server's game time
+const DRIFT_CHECK_INTERVAL: u64 = 100;
+
+/// Real-time length of one game tick at 20 TPS
+const TICK_MS: u64 = 50;
+
+/// Wall time between heartbeat lines while the timeline runs
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Totals for the most recent `run_tests_parallel` call
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunStats {
+    /// Game ticks advanced with `tick sprint`
+    pub ticks: u64,
+    /// Wall-clock time of the whole run, including setup and cleanup
+    pub duration: std::time::Duration,
+    /// Server game time advanced minus ticks advanced, at the last check;
+    /// `None` when the game time could not be read
+    pub drift: Option<i64>,
+    /// Tick the timeline was halted at by a stop tick, leaving every test area as it was
+    pub stopped_at: Option<u32>,
+}
+
+/// Outcome of one assertion of the most recent run
+#[derive(Debug, Clone, Serialize)]
+pub struct AssertionResult {
+    pub test_name: String,
+    /// Tick the assertion was scheduled for
+    pub tick: u32,
+    /// Spec-local position checked first; `None` for assertions without one
+    pub pos: Option<[i32; 3]>,
+    /// Expected value from the failure message, otherwise the entry's summary
+    pub expected: String,
+    /// Value found instead, or the failure message when it names none; `None` when passed
+    pub actual: Option<String>,
+    pub passed: bool,
+}
+
+impl AssertionResult {
+    /// Describe an assertion's outcome, taking expected and actual values from the
+    /// `expected:`/`actual:` lines of a failure when it has them
+    fn new(
+        test_name: &str,
+        tick: u32,
+        pos: Option<[i32; 3]>,
+        summary: String,
+        outcome: &Result<bool>,
+    ) -> Self {
+        let (expected, actual) = match outcome {
+            Err(e) => {
+                let message = e.to_string();
+                let line_value = |label: &str| {
+                    message
+                        .lines()
+                        .find_map(|line| line.trim_start().strip_prefix(label))
+                        .map(|value| value.trim().to_string())
+                };
+                match (line_value("expected:"), line_value("actual:")) {
+                    (Some(expected), Some(actual)) => (expected, Some(actual)),
+                    _ => (summary, Some(message)),
+                }
+            }
+            Ok(_) => (summary, None),
+        };
+        Self {
+            test_name: test_name.to_string(),
+            tick,
+            pos,
+            expected,
+            actual,
+            passed: outcome.is_ok(),
+        }
+    }
+}
+
 pub struct TestExecutor {
     bot: TestBot,
     use_chat_control: bool,
+    keep_on_failure: bool,
+    abort_on_setup_error: bool,
+    strict: bool,
+    /// Print the tick reached every few seconds, so a slow run doesn't look hung
+    heartbeat: bool,
+    /// Tick to halt the timeline at for inspection, unless a spec sets its own
+    stop_at_tick: Option<u32>,
+    quiet: bool,
+    machine_output: bool,
+    editor_output: bool,
+    verbose_failures: bool,
+    progress: Option<ProgressBar>,
+    tick_delay_ms: u64,
+    place_delay_ms: u64,
+    /// World-editing entries sent per tick before pausing; `None` sends them all at once
+    max_burst: Option<usize>,
+    world_load_timeout_ms: u64,
+    connect_commands: Vec<String>,
+    /// Seed for the server's random sequences, pinned before every run
+    seed: Option<u64>,
+    seed_commands: Vec<String>,
+    teleport: bool,
+    /// Largest gap between test areas that cleanup fills may bridge
+    cleanup_gap: i32,
+    flavor: ServerFlavor,
+    /// Set once the server has no way to freeze time; ticks are then waited out
+    real_time: bool,
+    /// Blocks captured by `assert_changed`, keyed by test name and world position
+    snapshots: HashMap<(String, [i32; 3]), String>,
+    /// Regions captured by `assert_stable`, keyed by test name and spec-local region,
+    /// with every block in x, y, z order
+    region_snapshots: HashMap<(String, [[i32; 3]; 2]), Vec<String>>,
+    /// Where the bot was last teleported to, if anywhere
+    bot_position: Option<[i32; 3]>,
+    /// World regions of the running tests with the bot position to clean them from and
+    /// what to fill them with, cleared once the run has cleaned up after itself
+    dirty_regions: Vec<([i32; 3], [[i32; 3]; 2], CleanupFill)>,
+    suite: Option<Suite>,
+    stats: RunStats,
+    /// Every assertion of the most recent run, grouped by test
+    assertion_results: Vec<AssertionResult>,
+    /// Tick reached and last tick of the running timeline; `None` before it starts
+    timeline_position: Option<(u32, u32)>,
+    /// Whether the server answers world edits; cleared once it stays silent
+    command_feedback: bool,
+    /// Namespaced block ids to expect in place of others, applied to expected blocks
+    block_aliases: HashMap<String, String>,
+    /// Rewrites of the blocks that placements and fills send, applied in order
+    block_transforms: Vec<BlockTransform>,
+    /// Copy the test areas at tick 0 so the run can be replayed
+    stash_enabled: bool,
+    /// Test areas copied at tick 0, with the bot position to reach them from
+    stash: Vec<([i32; 3], [[i32; 3]; 2])>,
+    /// How far above its test area each copy sits
+    stash_lift: i32,
+    /// Set while `replay` runs, so the areas are restored instead of cleaned
+    replaying: bool,
 }
 
 impl Default for TestExecutor {
@@ -16,6 +284,48 @@ impl Default for TestExecutor {
         Self {
             bot: TestBot::new(),
             use_chat_control: false,
+            keep_on_failure: false,
+            abort_on_setup_error: false,
+            strict: false,
+            heartbeat: false,
+            stop_at_tick: None,
+            quiet: false,
+            machine_output: false,
+            editor_output: false,
+            verbose_failures: false,
+            progress: None,
+            tick_delay_ms: DEFAULT_TICK_DELAY_MS,
+            place_delay_ms: DEFAULT_PLACE_DELAY_MS,
+            max_burst: None,
+            world_load_timeout_ms: DEFAULT_WORLD_LOAD_TIMEOUT_MS,
+            connect_commands: DEFAULT_CONNECT_COMMANDS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            seed: None,
+            seed_commands: DEFAULT_SEED_COMMANDS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            teleport: true,
+            cleanup_gap: 0,
+            flavor: ServerFlavor::default(),
+            real_time: false,
+            snapshots: HashMap::new(),
+            region_snapshots: HashMap::new(),
+            bot_position: None,
+            dirty_regions: Vec::new(),
+            suite: None,
+            stats: RunStats::default(),
+            assertion_results: Vec::new(),
+            timeline_position: None,
+            command_feedback: true,
+            block_aliases: HashMap::new(),
+            block_transforms: Vec::new(),
+            stash_enabled: false,
+            stash: Vec::new(),
+            stash_lift: 0,
+            replaying: false,
         }
     }
 }
@@ -29,6 +339,222 @@ impl TestExecutor {
         self.use_chat_control = enabled;
     }
 
+    pub fn set_keep_on_failure(&mut self, enabled: bool) {
+        self.keep_on_failure = enabled;
+    }
+
+    /// Copy the test areas above themselves at tick 0, so `replay` can restore them
+    pub fn set_snapshot(&mut self, enabled: bool) {
+        self.stash_enabled = enabled;
+    }
+
+    /// Stop a test's timeline at its first failed placement, fill, clone or command
+    pub fn set_abort_on_setup_error(&mut self, enabled: bool) {
+        self.abort_on_setup_error = enabled;
+    }
+
+    /// Treat spec validation warnings as errors
+    pub fn set_strict(&mut self, enabled: bool) {
+        self.strict = enabled;
+    }
+
+    /// Halt the timeline at the end of `tick`, then unfreeze time and leave every test
+    /// area as it is; a spec's `stop_at_tick` takes precedence
+    pub fn set_stop_at_tick(&mut self, tick: u32) {
+        self.stop_at_tick = Some(tick);
+    }
+
+    /// Print the current tick and the tests still running every few seconds of the timeline
+    pub fn set_heartbeat(&mut self, enabled: bool) {
+        self.heartbeat = enabled;
+    }
+
+    /// Suppress per-action output and show a progress bar over the global timeline
+    pub fn set_quiet(&mut self, enabled: bool) {
+        self.quiet = enabled;
+    }
+
+    /// Send all decorative output to stderr so stdout only carries a machine-readable report
+    pub fn set_machine_output(&mut self, enabled: bool) {
+        self.machine_output = enabled;
+    }
+
+    /// Print a `FAIL file:... line:...` line for every failure, for editors to jump to
+    pub fn set_editor_output(&mut self, enabled: bool) {
+        self.editor_output = enabled;
+    }
+
+    /// List the entries a test ran before its first failure
+    pub fn set_verbose_failures(&mut self, enabled: bool) {
+        self.verbose_failures = enabled;
+    }
+
+    pub fn set_tick_delay(&mut self, delay_ms: u64) {
+        self.tick_delay_ms = delay_ms;
+    }
+
+    /// Pause between the blocks of a `place_each`; zero sends them back to back
+    pub fn set_place_delay(&mut self, delay_ms: u64) {
+        self.place_delay_ms = delay_ms;
+    }
+
+    /// Expect the block on the right wherever a spec expects the one on the left, e.g.
+    /// `grass` -> `short_grass` for servers that renamed it; bare ids mean `minecraft:`
+    pub fn set_block_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.block_aliases = aliases
+            .into_iter()
+            .map(|(from, to)| (namespaced(&from), namespaced(&to)))
+            .collect();
+    }
+
+    /// Rewrite every block a placement or fill sends, e.g. to a mod's namespace
+    pub fn set_block_transforms(&mut self, transforms: Vec<BlockTransform>) {
+        self.block_transforms = transforms;
+    }
+
+    /// A spec block as it will be sent, transformed and then validated
+    fn outgoing_block<'a>(&self, block: &'a str) -> Result<Cow<'a, str>> {
+        let block = transform_block(block, &self.block_transforms);
+        validate_block(&block)?;
+        Ok(block)
+    }
+
+    /// Whether a block read from the world is the spec's expected block, after aliasing
+    fn block_matches(&self, expected: &str, actual: &BlockInfo, ignore: &[String]) -> bool {
+        blocks_match_ignoring(&apply_alias(expected, &self.block_aliases), actual, ignore)
+    }
+
+    /// Pause after every `max_burst` world-editing entries of a tick so the server keeps up
+    pub fn set_max_burst(&mut self, max_burst: usize) {
+        self.max_burst = Some(max_burst.max(1));
+    }
+
+    /// How long to wait for the chunk of each test's first action before giving up
+    pub fn set_world_load_timeout(&mut self, timeout_ms: u64) {
+        self.world_load_timeout_ms = timeout_ms;
+    }
+
+    /// Commands run once after connecting; empty to skip the post-connect setup
+    pub fn set_connect_commands(&mut self, commands: Vec<String>) {
+        self.connect_commands = commands;
+    }
+
+    /// Pin the server's randomness to `seed` before every run
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Commands that pin randomness, with `{seed}` standing for the seed
+    pub fn set_seed_commands(&mut self, commands: Vec<String>) {
+        self.seed_commands = commands;
+    }
+
+    /// Teleport the bot next to each group of test areas before touching them
+    pub fn set_teleport(&mut self, enabled: bool) {
+        self.teleport = enabled;
+    }
+
+    /// Let cleanup fills bridge gaps of up to `blocks` between different test areas
+    /// Only for areas laid out with that spacing, whose gaps hold nothing; neighbouring
+    /// areas of the same size are then cleaned with a single fill. Off (0) by default.
+    pub fn set_cleanup_gap(&mut self, blocks: i32) {
+        self.cleanup_gap = blocks.max(0);
+    }
+
+    pub fn set_server_flavor(&mut self, flavor: ServerFlavor) {
+        self.flavor = flavor;
+        self.real_time = flavor == ServerFlavor::Legacy;
+    }
+
+    pub fn set_username(&mut self, username: String) {
+        self.bot.set_username(username);
+    }
+
+    /// Append every command sent to `path`, under comments naming its tick and test
+    pub fn set_trace(&mut self, path: &std::path::Path) -> Result<()> {
+        self.bot.set_trace(path)
+    }
+
+    /// How long connecting waits for the bot to initialize; see `TestBot::set_init_timeout`
+    pub fn set_init_timeout(&mut self, timeout: std::time::Duration) {
+        self.bot.set_init_timeout(timeout);
+    }
+
+    /// How long connecting waits for the bot to enter the game; see `TestBot::set_join_timeout`
+    pub fn set_join_timeout(&mut self, timeout: std::time::Duration) {
+        self.bot.set_join_timeout(timeout);
+    }
+
+    /// Limit the bot to `per_second` commands a second; see `TestBot::set_command_rate`
+    pub fn set_command_rate(&mut self, per_second: u32) {
+        self.bot.set_command_rate(per_second);
+    }
+
+    /// Return from each command once it is queued rather than sent
+    pub fn set_return_when_queued(&mut self, enabled: bool) {
+        self.bot.set_return_when_queued(enabled);
+    }
+
+    /// Make the username unique with a short random suffix, e.g. for parallel CI jobs
+    pub fn randomize_username(&mut self) {
+        self.bot.add_random_suffix();
+    }
+
+    pub fn username(&self) -> &str {
+        self.bot.username()
+    }
+
+    pub fn set_suite(&mut self, suite: Suite) {
+        self.suite = Some(suite);
+    }
+
+    /// Version the server reported when the bot connected
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.bot.server_info()
+    }
+
+    pub fn stats(&self) -> RunStats {
+        self.stats
+    }
+
+    /// Every assertion of the most recent run in test order, then in the order checked
+    pub fn assertion_results(&self) -> &[AssertionResult] {
+        &self.assertion_results
+    }
+
+    /// How far the current or last run got: the tick reached and the timeline's last tick
+    /// `None` while the run is still in setup, before its first tick.
+    pub fn timeline_position(&self) -> Option<(u32, u32)> {
+        self.timeline_position
+    }
+
+    /// Apply suite setup or teardown entries in order at the suite offset
+    /// Assertions are checked but never counted toward test results
+    async fn run_suite_phase(
+        &mut self,
+        phase: &str,
+        entries: &[TimelineEntry],
+        offset: [i32; 3],
+    ) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        self.status(format!(
+            "{} Running suite {} ({} entries)...",
+            "→".blue(),
+            phase,
+            entries.len()
+        ));
+        for entry in entries {
+            if let Err(e) = self.execute_action(0, &entry.action_type, 0, offset).await {
+                anyhow::bail!("Suite {} failed: {}", phase, e);
+            }
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        Ok(())
+    }
+
     /// Returns true to continue, false to step to next tick only
     async fn wait_for_step(&mut self, reason: &str) -> Result<bool> {
         println!(
@@ -114,75 +640,385 @@ impl TestExecutor {
         }
     }
 
+    /// Print a run status line, on stderr when a machine-readable report owns stdout
+    fn status(&self, line: String) {
+        if self.machine_output {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    /// Print a per-action log line unless quiet mode is enabled
+    fn log_action(&self, line: String) {
+        if !self.quiet {
+            self.status(line);
+        }
+    }
+
+    /// Print a line that must always be shown, without breaking the progress bar
+    fn log_always(&self, line: String) {
+        match &self.progress {
+            Some(progress) => progress.println(line),
+            None => self.status(line),
+        }
+    }
+
     fn apply_offset(&self, pos: [i32; 3], offset: [i32; 3]) -> [i32; 3] {
         [pos[0] + offset[0], pos[1] + offset[1], pos[2] + offset[2]]
     }
 
-    /// Poll for a block at the given position with retries
-    /// This handles timing issues in CI environments where block updates may take longer
-    async fn poll_block_with_retry(
-        &self,
-        world_pos: [i32; 3],
-        expected_block: &str,
-        max_attempts: u32,
-        delay_ms: u64,
-    ) -> Result<Option<String>> {
-        let expected_name = expected_block
-            .trim_start_matches("minecraft:")
-            .to_lowercase()
-            .replace("_", "");
+    /// Fill the world-space cleanup regions of the given tests with their cleanup blocks,
+    /// one partition at a time
+    /// Regions that together form a box and share a cleanup fill are merged into a single
+    /// fill, so neighbouring areas need far fewer commands; with `set_cleanup_gap`, so are
+    /// areas that only the layout's spacing keeps apart.
+    async fn clean_tests(
+        &mut self,
+        test_indices: &[usize],
+        world_regions: &[Vec<[[i32; 3]; 2]>],
+        extras: &[SpecExtras],
+        partitions: &[usize],
+        stand_points: &[[i32; 3]],
+    ) -> Result<()> {
+        let mut by_partition: BTreeMap<(usize, CleanupFill), (usize, Vec<Vec<[[i32; 3]; 2]>>)> =
+            BTreeMap::new();
+        for &test_idx in test_indices {
+            by_partition
+                .entry((partitions[test_idx], extras[test_idx].cleanup_fill.clone()))
+                .or_insert_with(|| (test_idx, Vec::new()))
+                .1
+                .push(world_regions[test_idx].clone());
+        }
 
-        for attempt in 0..max_attempts {
-            let block = self.bot.get_block(world_pos).await?;
+        let mut region_count = 0;
+        let mut fill_count = 0;
+        for ((_, fill), (first_test, areas)) in by_partition {
+            self.approach(stand_points[first_test]).await?;
+            region_count += areas.iter().map(Vec::len).sum::<usize>();
+            fill_count += self.fill_regions(&areas, &fill, self.cleanup_gap).await?;
+        }
+        tracing::debug!(
+            "Cleaned {} regions with {} fill commands",
+            region_count,
+            fill_count
+        );
+        Ok(())
+    }
 
-            // Check if the block matches what we expect
-            if let Some(ref actual) = block {
-                let actual_lower = actual.to_lowercase();
-                if actual_lower.contains(&expected_name)
-                    || actual_lower.replace("_", "").contains(&expected_name)
-                {
-                    return Ok(block);
-                }
-            }
+    /// Fill the world-space regions of some areas with a cleanup fill, merged where
+    /// possible and across gaps of up to `gap` blocks between areas; returns the fills sent
+    /// The floor is the bottom layer of each region before merging, so stacked regions
+    /// each get their own.
+    async fn fill_regions(
+        &mut self,
+        areas: &[Vec<[[i32; 3]; 2]>],
+        fill: &CleanupFill,
+        gap: i32,
+    ) -> Result<usize> {
+        let mut merged = layout::merge_area_regions(areas, gap)
+            .into_iter()
+            .map(|region| (region, fill.block.as_str()))
+            .collect::<Vec<_>>();
+        if let Some(floor) = &fill.floor {
+            let floors: Vec<Vec<[[i32; 3]; 2]>> = areas
+                .iter()
+                .map(|regions| {
+                    regions
+                        .iter()
+                        .map(|region| {
+                            let [min, max] = normalized(*region);
+                            [min, [max[0], min[1], max[2]]]
+                        })
+                        .collect()
+                })
+                .collect();
+            merged.extend(
+                layout::merge_area_regions(&floors, gap)
+                    .into_iter()
+                    .map(|region| (region, floor.as_str())),
+            );
+        }
+        for ([min, max], block) in &merged {
+            let cmd = format!(
+                "fill {} {} {} {} {} {} {}",
+                min[0], min[1], min[2], max[0], max[1], max[2], block
+            );
+            self.bot.send_command(&cmd).await?;
+        }
+        Ok(merged.len())
+    }
 
-            // If not the last attempt, wait before retrying
-            if attempt < max_attempts - 1 {
-                tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
-            }
+    /// Clone every test area to a copy `stash_lift` blocks above it
+    /// The copies stay in the same chunks as their areas, so they are always loaded.
+    async fn save_stash(&mut self, regions: Vec<([i32; 3], [[i32; 3]; 2])>) -> Result<()> {
+        let bottom = regions.iter().map(|(_, r)| r[0][1].min(r[1][1])).min();
+        let top = regions.iter().map(|(_, r)| r[0][1].max(r[1][1])).max();
+        let (Some(bottom), Some(top)) = (bottom, top) else {
+            return Ok(());
+        };
+        let lift = top - bottom + 1 + STASH_GAP;
+        if top + lift > WORLD_TOP_Y {
+            anyhow::bail!(
+                "No room for the tick-0 snapshot above the test areas: the copies would reach y={}, above the world's top at y={}",
+                top + lift,
+                WORLD_TOP_Y
+            );
+        }
+
+        for (stand_point, region) in &regions {
+            self.approach(*stand_point).await?;
+            let [min, max] = normalized(*region);
+            let cmd = format!(
+                "clone {} {} {} {} {} {} {} {} {}",
+                min[0],
+                min[1],
+                min[2],
+                max[0],
+                max[1],
+                max[2],
+                min[0],
+                min[1] + lift,
+                min[2]
+            );
+            self.send_world_command(0, &cmd).await?;
+        }
+        self.stash = regions;
+        self.stash_lift = lift;
+        Ok(())
+    }
+
+    /// Clone the tick-0 copies back over their test areas
+    async fn restore_stash(&mut self) -> Result<()> {
+        for (stand_point, region) in self.stash.clone() {
+            self.approach(stand_point).await?;
+            let [min, max] = normalized(region);
+            let cmd = format!(
+                "clone {} {} {} {} {} {} {} {} {}",
+                min[0],
+                min[1] + self.stash_lift,
+                min[2],
+                max[0],
+                max[1] + self.stash_lift,
+                max[2],
+                min[0],
+                min[1],
+                min[2]
+            );
+            self.send_world_command(0, &cmd).await?;
+        }
+        Ok(())
+    }
+
+    /// Fill the tick-0 copies with air and forget them; returns the regions cleared
+    pub async fn clear_snapshot(&mut self) -> Result<usize> {
+        let stash = std::mem::take(&mut self.stash);
+        for (stand_point, region) in &stash {
+            self.approach(*stand_point).await?;
+            let lifted = region.map(|corner| [corner[0], corner[1] + self.stash_lift, corner[2]]);
+            self.fill_regions(&[vec![lifted]], &CleanupFill::default(), 0)
+                .await?;
+        }
+        Ok(stash.len())
+    }
+
+    /// Run the tests again from tick 0, restoring their areas from the snapshot
+    /// instead of cleaning them; needs a previous run with `set_snapshot(true)`
+    pub async fn replay(
+        &mut self,
+        tests_with_offsets: &[(TestSpec, [i32; 3])],
+        extras: &[SpecExtras],
+        break_after_setup: bool,
+    ) -> Result<Vec<TestResult>> {
+        anyhow::ensure!(
+            !self.stash.is_empty(),
+            "No snapshot to replay from; the tests have to run once with a snapshot first"
+        );
+        self.replaying = true;
+        let results = self
+            .run_tests_parallel(tests_with_offsets, extras, break_after_setup)
+            .await;
+        self.replaying = false;
+        results
+    }
+
+    /// Undo what an interrupted run left behind: unfreeze time and fill every test area
+    /// of the run with its cleanup block. Returns the number of regions cleared.
+    pub async fn restore_after_interrupt(&mut self) -> Result<usize> {
+        if let Some(progress) = self.progress.take() {
+            progress.finish_and_clear();
         }
+        self.unfreeze_time().await?;
 
-        // Return whatever we have after all retries
-        self.bot.get_block(world_pos).await
+        let dirty = std::mem::take(&mut self.dirty_regions);
+        let mut by_stand_point: BTreeMap<([i32; 3], &CleanupFill), Vec<[[i32; 3]; 2]>> =
+            BTreeMap::new();
+        for (stand_point, region, fill) in &dirty {
+            by_stand_point
+                .entry((*stand_point, fill))
+                .or_default()
+                .push(*region);
+        }
+        for ((stand_point, fill), regions) in by_stand_point {
+            self.approach(stand_point).await?;
+            self.fill_regions(&[regions], fill, 0).await?;
+        }
+        let stashed = self.clear_snapshot().await?;
+        Ok(dirty.len() + stashed)
     }
 
-    /// Poll for a block state property at the given position with retries
-    async fn poll_block_state_with_retry(
+    /// Read a block until it is one of `acceptable` or the settle budget runs out
+    /// Returns the last block read and the total time waited for it.
+    async fn settle_block(
         &self,
         world_pos: [i32; 3],
-        state: &str,
-        max_attempts: u32,
-        delay_ms: u64,
-    ) -> Result<Option<String>> {
-        for attempt in 0..max_attempts {
-            let state_value = self.bot.get_block_state_property(world_pos, state).await?;
-            if state_value.is_some() {
-                return Ok(state_value);
+        acceptable: &[String],
+        ignore: &[String],
+    ) -> Result<(BlockInfo, u64)> {
+        let mut waited = 0;
+        let mut delays = settle_delays();
+        loop {
+            let block = self.bot.get_block(world_pos).await?;
+            if acceptable
+                .iter()
+                .any(|expected| self.block_matches(expected, &block, ignore))
+            {
+                return Ok((block, waited));
             }
-            if attempt < max_attempts - 1 {
-                tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            let Some(delay) = delays.next() else {
+                return Ok((block, waited));
+            };
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+            waited += delay;
+        }
+    }
+
+    /// Read a block state property until `accepts` its value or the settle budget runs out
+    /// Returns the last value read and the total time waited for it.
+    async fn settle_block_state(
+        &self,
+        world_pos: [i32; 3],
+        state: &str,
+        accepts: impl Fn(&str) -> bool,
+    ) -> Result<(Option<String>, u64)> {
+        let mut waited = 0;
+        let mut delays = settle_delays();
+        loop {
+            let value = self.bot.get_block_property(world_pos, state).await?;
+            if value.as_deref().is_some_and(&accepts) {
+                return Ok((value, waited));
             }
+            let Some(delay) = delays.next() else {
+                return Ok((value, waited));
+            };
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+            waited += delay;
         }
-        Ok(None)
     }
 
     pub async fn connect(&mut self, server: &str) -> Result<()> {
-        self.bot.connect(server).await
+        self.bot.connect(server).await?;
+        self.run_connect_commands().await
     }
 
-    /// Sprint ticks and capture the time taken from server output
-    /// Returns the ms per tick from the server's sprint completion message
-    async fn sprint_ticks(&mut self, ticks: u32) -> Result<u64> {
-        // Clear any pending chat messages
+    /// Run the post-connect commands, warning when the server rejects them
+    /// A rejection means the bot is not an operator, so every later command fails too.
+    async fn run_connect_commands(&mut self) -> Result<()> {
+        for command in self.connect_commands.clone() {
+            let command = command.trim_start_matches('/');
+            self.drain_chat().await;
+            self.bot.send_command(command).await?;
+            let feedback = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(500))
+                .await;
+
+            match feedback {
+                Some(message)
+                    if message.contains("Unknown or incomplete command")
+                        || message.contains("permission") =>
+                {
+                    self.status(format!(
+                        "  {} {}",
+                        "Warning:".yellow().bold(),
+                        format!(
+                            "The bot lacks operator permissions: '/{}' was rejected. Every setblock, fill and tick command will fail; run `op {}` on the server console",
+                            command,
+                            self.bot.username()
+                        )
+                        .bold()
+                    ));
+                    return Ok(());
+                }
+                Some(message) if command.starts_with("gamemode") && !message.contains("game mode") => {
+                    self.status(format!(
+                        "  {} '/{}' did not change the game mode: {}",
+                        "Warning:".yellow().bold(),
+                        command,
+                        message
+                    ));
+                }
+                Some(message) => tracing::debug!("'/{}' answered: {}", command, message),
+                None if command.starts_with("gamemode") => self.status(format!(
+                    "  {} Could not confirm the game mode: no feedback for '/{}' (the bot may already be in that mode)",
+                    "Warning:".yellow().bold(),
+                    command
+                )),
+                None => tracing::debug!("No feedback for '/{}'", command),
+            }
+        }
+        Ok(())
+    }
+
+    /// Teleport the bot to `target` unless it is already there, then wait for its chunk
+    async fn approach(&mut self, target: [i32; 3]) -> Result<()> {
+        if !self.teleport || self.bot_position == Some(target) {
+            return Ok(());
+        }
+
+        self.bot
+            .send_command(&format!("tp @s {} {} {}", target[0], target[1], target[2]))
+            .await?;
+        self.bot
+            .wait_for_chunk(
+                target,
+                std::time::Duration::from_millis(self.world_load_timeout_ms),
+            )
+            .await?;
+        self.bot_position = Some(target);
+        tracing::debug!("Bot teleported to {:?}", target);
+        Ok(())
+    }
+
+    /// Try a single reconnect after the bot dropped out mid-run
+    /// Time is frozen again on success so the timeline can continue from `tick`, starting
+    /// with `next_test`, the first test that had not finished when the connection dropped.
+    async fn recover_connection(&mut self, tick: u32, next_test: usize) -> Result<()> {
+        self.status(format!(
+            "    {} Lost connection to server at tick {}, reconnecting...",
+            "⚠".yellow().bold(),
+            tick
+        ));
+
+        if let Err(e) = self.bot.reconnect().await {
+            anyhow::bail!("Lost connection to server after tick {}: {}", tick, e);
+        }
+        // The bot respawns at the world spawn after reconnecting
+        self.bot_position = None;
+
+        self.freeze_time().await?;
+        self.status(format!(
+            "    {} Reconnected, resuming at tick {} from test #{}",
+            "✓".green(),
+            tick,
+            next_test + 1
+        ));
+        tracing::info!("Resumed at tick {} from test index {}", tick, next_test);
+        Ok(())
+    }
+
+    /// Discard chat messages received so far
+    async fn drain_chat(&mut self) {
         while self
             .bot
             .recv_chat_timeout(std::time::Duration::from_millis(10))
@@ -191,110 +1027,678 @@ impl TestExecutor {
         {
             // Discard old messages
         }
+    }
 
-        // Send the sprint command
-        self.bot
-            .send_command(&format!("tick sprint {}", ticks))
-            .await?;
+    /// Send a setblock, fill or clone and fail with the server's error if it rejects it
+    /// Without this, a rejected edit only shows up as a confusing assertion failure later.
+    async fn send_world_command(&mut self, tick: u32, cmd: &str) -> Result<()> {
+        self.drain_chat().await;
+        self.bot.send_command(cmd).await?;
+        if let Some(message) = self.command_rejection(1).await {
+            anyhow::bail!("Server rejected '/{}' at tick {}: {}", cmd, tick, message);
+        }
+        Ok(())
+    }
 
-        // Wait for the "Sprint completed" message
-        // Server message format: "Sprint completed with X ticks per second, or Y ms per tick"
-        let timeout = std::time::Duration::from_secs(30);
-        let start = std::time::Instant::now();
+    /// Wait for the feedback of `count` world edits just sent and return the first error
+    /// A server that never answers (e.g. with `sendCommandFeedback` off) is not waited
+    /// on again.
+    async fn command_rejection(&mut self, count: usize) -> Option<String> {
+        if !self.command_feedback {
+            return None;
+        }
 
-        while start.elapsed() < timeout {
-            if let Some(message) = self
+        let mut acknowledged = 0;
+        let start = std::time::Instant::now();
+        while acknowledged < count
+            && start.elapsed() < std::time::Duration::from_millis(COMMAND_FEEDBACK_TIMEOUT_MS)
+        {
+            let Some(message) = self
                 .bot
-                .recv_chat_timeout(std::time::Duration::from_millis(100))
+                .recv_chat_timeout(std::time::Duration::from_millis(50))
                 .await
+            else {
+                continue;
+            };
+            if is_player_chat(&message) {
+                continue;
+            }
+            if is_command_rejection(&message) {
+                return Some(message);
+            }
+            if COMMAND_ACKNOWLEDGEMENTS
+                .iter()
+                .any(|fragment| message.contains(fragment))
             {
-                // Look for "Sprint completed" message
-                if message.contains("Sprint completed") {
-                    // Try to extract ms per tick
-                    // Format: "... or X ms per tick"
-                    if let Some(ms_part) = message.split("or ").nth(1)
-                        && let Some(ms_str) = ms_part.split(" ms per tick").next()
-                            && let Ok(ms) = ms_str.trim().parse::<f64>() {
-                                let ms_rounded = ms.ceil() as u64;
-                                println!(
-                                    "    {} Sprint {} ticks completed in {} ms per tick",
-                                    "⚡".dimmed(),
-                                    ticks,
-                                    ms_rounded
-                                );
-                                // Return total time: ms per tick * number of ticks
-                                return Ok(ms_rounded * ticks as u64);
-                            }
-                    // If we found the message but couldn't parse, use default
-                    println!(
-                        "    {} Sprint {} ticks completed (timing not parsed)",
-                        "⚡".dimmed(),
-                        ticks
-                    );
-                    return Ok(200);
-                }
+                acknowledged += 1;
             }
         }
 
-        // Timeout - return default
-        println!(
-            "    {} Sprint {} ticks (no completion message received)",
-            "⚡".dimmed(),
-            ticks
-        );
-        Ok(200)
+        if acknowledged == 0 {
+            self.command_feedback = false;
+            tracing::warn!(
+                "No feedback for setblock/fill commands; rejected world edits will only show up as assertion failures (is the sendCommandFeedback gamerule off?)"
+            );
+        }
+        None
+    }
+
+    /// Freeze game time, switching to real-time waits if the server has no `/tick`
+    /// Fails if the freeze does not take effect because the bot lacks operator
+    /// permission, since every `tick step` would then be ignored as well.
+    async fn freeze_time(&mut self) -> Result<()> {
+        if self.real_time {
+            return Ok(());
+        }
+
+        self.drain_chat().await;
+        self.bot.send_command("tick freeze").await?;
+
+        // Vanilla answers "The game is frozen"; an unknown command means no `/tick`,
+        // or that the bot is not allowed to see it
+        let mut frozen = false;
+        let mut unknown = false;
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_millis(500) {
+            let Some(message) = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(100))
+                .await
+            else {
+                continue;
+            };
+            if message.contains("frozen") {
+                frozen = true;
+                break;
+            }
+            if message.contains("permission") {
+                self.bail_without_operator("tick freeze")?;
+            }
+            if message.contains("Unknown or incomplete command") {
+                unknown = true;
+                break;
+            }
+        }
+
+        if unknown {
+            // Non-operators see every operator command as unknown, `/time` included
+            if self.query_game_time().await?.is_none() {
+                self.bail_without_operator("tick freeze")?;
+            }
+            self.real_time = true;
+            self.status(format!(
+                "  {} Server has no /tick command; falling back to real-time waits, timing assertions may be flaky (try --server-flavor)",
+                "Warning:".yellow().bold()
+            ));
+            return Ok(());
+        }
+
+        if !frozen && !self.tick_frozen().await? {
+            self.bail_without_operator("tick freeze")?;
+        }
+        Ok(())
+    }
+
+    /// Whether `/tick query` reports the game as frozen
+    async fn tick_frozen(&mut self) -> Result<bool> {
+        self.drain_chat().await;
+        self.bot.send_command("tick query").await?;
+
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_millis(500) {
+            if let Some(message) = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(100))
+                .await
+                && message.contains("frozen")
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Abort the run because `command` had no effect for lack of operator permission
+    fn bail_without_operator(&self, command: &str) -> Result<()> {
+        anyhow::bail!(
+            "'/{}' had no effect: the bot needs operator permission to freeze and step game time. Run `op {}` on the server console and try again",
+            command,
+            self.bot.username()
+        );
+    }
+
+    /// Run the seed commands, if a seed was given, warning when the server rejects one
+    async fn pin_randomness(&mut self) -> Result<()> {
+        let Some(seed) = self.seed else {
+            return Ok(());
+        };
+
+        for command in self.seed_commands.clone() {
+            let command = command
+                .trim_start_matches('/')
+                .replace("{seed}", &seed.to_string());
+            self.drain_chat().await;
+            self.bot.send_command(&command).await?;
+            if let Some(message) = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(500))
+                .await
+                && message.contains("Unknown or incomplete command")
+            {
+                self.status(format!(
+                    "  {} '/{}' is not supported by this server; randomness is not pinned",
+                    "Warning:".yellow().bold(),
+                    command
+                ));
+            }
+        }
+        self.status(format!(
+            "{} Random sequences seeded with {}",
+            "→".blue(),
+            seed
+        ));
+        Ok(())
+    }
+
+    /// Read the server's game time from `/time query gametime` feedback
+    async fn query_game_time(&mut self) -> Result<Option<u64>> {
+        self.drain_chat().await;
+        self.bot.send_command("time query gametime").await?;
+
+        // Server message format: "The time is X"
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_secs(1) {
+            if let Some(message) = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(100))
+                .await
+                && let Some(time) = message.split("The time is ").nth(1)
+                && let Ok(time) = time.trim().parse::<u64>()
+            {
+                return Ok(Some(time));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Compare how far the server's game time moved since `base` with the ticks advanced
+    /// A mismatch means time was not frozen, so tick-based timing cannot be trusted.
+    async fn check_drift(&mut self, base: Option<(u64, u64)>) -> Result<()> {
+        let Some((base_time, base_ticks)) = base else {
+            return Ok(());
+        };
+        let Some(game_time) = self.query_game_time().await? else {
+            return Ok(());
+        };
+
+        let expected = self.stats.ticks - base_ticks;
+        let observed = game_time.saturating_sub(base_time);
+        let drift = observed as i64 - expected as i64;
+        if drift != 0 && self.stats.drift != Some(drift) {
+            self.log_always(format!(
+                "    {} Game time drift: server advanced {} ticks, flintcli advanced {} ({:+})",
+                "⚠".yellow().bold(),
+                observed,
+                expected,
+                drift
+            ));
+        }
+        self.stats.drift = Some(drift);
+        Ok(())
+    }
+
+    /// Read one tag of a block entity with `/data get block`, as the SNBT the server prints
+    /// `None` when the block entity has no such tag. `kind` names the expected block
+    /// entity, e.g. `container`, in the error for a block without one.
+    async fn query_block_data(
+        &mut self,
+        pos: [i32; 3],
+        world_pos: [i32; 3],
+        path: &str,
+        kind: &str,
+    ) -> Result<Option<String>> {
+        self.drain_chat().await;
+        self.bot
+            .send_command(&format!(
+                "data get block {} {} {} {}",
+                world_pos[0], world_pos[1], world_pos[2], path
+            ))
+            .await?;
+
+        // Server message format: "X, Y, Z has the following block data: [...]"
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_secs(1) {
+            let Some(message) = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(100))
+                .await
+            else {
+                continue;
+            };
+            if let Some((_, data)) = message.split_once("has the following block data:") {
+                return Ok(Some(data.trim().to_string()));
+            }
+            if message.contains("Found no elements matching") {
+                return Ok(None);
+            }
+            if message.contains("not a block entity") {
+                let block = self.bot.get_block(world_pos).await?;
+                anyhow::bail!(
+                    "Block at [{}, {}, {}] is not a {}: {} has no block entity",
+                    pos[0],
+                    pos[1],
+                    pos[2],
+                    kind,
+                    block
+                );
+            }
+        }
+        anyhow::bail!(
+            "Could not read the {} at [{}, {}, {}]: no answer to /data get block",
+            kind,
+            pos[0],
+            pos[1],
+            pos[2]
+        );
+    }
+
+    /// Read a container's stacks as (item id, count)
+    async fn query_items(
+        &mut self,
+        pos: [i32; 3],
+        world_pos: [i32; 3],
+    ) -> Result<Vec<(String, u32)>> {
+        // Containers leave out the Items tag entirely while empty
+        Ok(self
+            .query_block_data(pos, world_pos, "Items", "container")
+            .await?
+            .map(|items| parse_items(&items))
+            .unwrap_or_default())
+    }
+
+    /// Read the plain text of a sign's four front lines
+    async fn query_sign(&mut self, pos: [i32; 3], world_pos: [i32; 3]) -> Result<Vec<String>> {
+        match self
+            .query_block_data(pos, world_pos, "front_text.messages", "sign")
+            .await?
+        {
+            Some(messages) => Ok(parse_sign_lines(&messages)),
+            None => {
+                let block = self.bot.get_block(world_pos).await?;
+                anyhow::bail!(
+                    "Block at [{}, {}, {}] has no sign text: {} has no front_text (signs before 1.20 are not supported)",
+                    pos[0],
+                    pos[1],
+                    pos[2],
+                    block
+                );
+            }
+        }
+    }
+
+    /// Whether an `execute if ...` condition holds, from its "Test passed/failed" feedback
+    async fn check_condition(&mut self, condition: &str) -> Result<bool> {
+        self.drain_chat().await;
+        self.bot
+            .send_command(&format!("execute {}", condition))
+            .await?;
+
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_secs(1) {
+            let Some(message) = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(100))
+                .await
+            else {
+                continue;
+            };
+            if message.contains("Test passed") {
+                return Ok(true);
+            }
+            if message.contains("Test failed") {
+                return Ok(false);
+            }
+            if message.contains("Unknown or incomplete command") || message.contains("<--[HERE]") {
+                anyhow::bail!("Server rejected '/execute {}': {}", condition, message);
+            }
+        }
+        anyhow::bail!("No answer to '/execute {}'", condition);
+    }
+
+    /// Light level at a world position, found by bisecting with location predicates
+    /// Azalea does not keep the chunk light data, so the server is asked instead.
+    async fn query_light(&mut self, world_pos: [i32; 3]) -> Result<u8> {
+        let (mut low, mut high) = (0u8, 15u8);
+        while low < high {
+            let mid = (low + high) / 2;
+            let at_most_mid = self
+                .check_condition(&format!(
+                    "positioned {} {} {} if predicate {{condition:\"minecraft:location_check\",predicate:{{light:{{light:{{min:{},max:{}}}}}}}}}",
+                    world_pos[0], world_pos[1], world_pos[2], low, mid
+                ))
+                .await?;
+            if at_most_mid {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        Ok(low)
+    }
+
+    async fn unfreeze_time(&mut self) -> Result<()> {
+        if self.real_time {
+            return Ok(());
+        }
+        self.bot.send_command("tick unfreeze").await
+    }
+
+    /// Sprint ticks and capture the time taken from server output
+    /// Returns the ms per tick from the server's sprint completion message
+    async fn sprint_ticks(&mut self, ticks: u32) -> Result<u64> {
+        if self.real_time {
+            let wait_ms = u64::from(ticks) * TICK_MS;
+            tokio::time::sleep(tokio::time::Duration::from_millis(wait_ms)).await;
+            self.stats.ticks += u64::from(ticks);
+            return Ok(wait_ms);
+        }
+
+        // Clear any pending chat messages
+        self.drain_chat().await;
+
+        // Send the sprint command
+        self.bot
+            .send_command(&format!("tick sprint {}", ticks))
+            .await?;
+        self.stats.ticks += u64::from(ticks);
+
+        // Wait for the "Sprint completed" message
+        // Server message format: "Sprint completed with X ticks per second, or Y ms per tick"
+        let timeout = std::time::Duration::from_secs(30);
+        let start = std::time::Instant::now();
+
+        while start.elapsed() < timeout {
+            if let Some(message) = self
+                .bot
+                .recv_chat_timeout(std::time::Duration::from_millis(100))
+                .await
+            {
+                // Look for "Sprint completed" message
+                if message.contains("Sprint completed") {
+                    // Try to extract ms per tick
+                    // Format: "... or X ms per tick"
+                    if let Some(ms_part) = message.split("or ").nth(1)
+                        && let Some(ms_str) = ms_part.split(" ms per tick").next()
+                        && let Ok(ms) = ms_str.trim().parse::<f64>()
+                    {
+                        let ms_rounded = ms.ceil() as u64;
+                        self.log_action(format!(
+                            "    {} Sprint {} ticks completed in {} ms per tick",
+                            "⚡".dimmed(),
+                            ticks,
+                            ms_rounded
+                        ));
+                        // Return total time: ms per tick * number of ticks
+                        return Ok(ms_rounded * ticks as u64);
+                    }
+                    // If we found the message but couldn't parse, use default
+                    self.log_action(format!(
+                        "    {} Sprint {} ticks completed (timing not parsed)",
+                        "⚡".dimmed(),
+                        ticks
+                    ));
+                    return Ok(200);
+                }
+            }
+        }
+
+        // Timeout - return default
+        self.log_action(format!(
+            "    {} Sprint {} ticks (no completion message received)",
+            "⚡".dimmed(),
+            ticks
+        ));
+        Ok(200)
     }
 
     pub async fn run_tests_parallel(
         &mut self,
         tests_with_offsets: &[(TestSpec, [i32; 3])],
+        extras: &[SpecExtras],
         break_after_setup: bool,
     ) -> Result<Vec<TestResult>> {
-        println!(
+        anyhow::ensure!(
+            extras.len() == tests_with_offsets.len(),
+            "Expected spec extras for each of the {} tests, got {}",
+            tests_with_offsets.len(),
+            extras.len()
+        );
+        let run_start = std::time::Instant::now();
+        self.stats = RunStats::default();
+        self.assertion_results.clear();
+        self.timeline_position = None;
+        self.bot.set_trace_context(None, None);
+
+        // Every position of a test is moved by its origin and then by its offset
+        let offsets: Vec<[i32; 3]> = tests_with_offsets
+            .iter()
+            .zip(extras)
+            .map(|((_, offset), test_extras)| layout::apply_origin(*offset, test_extras.origin))
+            .collect();
+
+        self.status(format!(
             "{} Running {} tests in parallel\n",
             "→".blue().bold(),
             tests_with_offsets.len()
-        );
+        ));
 
         // Build global merged timeline using flint-core
         let aggregate = TimelineAggregate::from_tests(tests_with_offsets);
 
-        println!("  Global timeline: {} ticks", aggregate.max_tick);
-        println!(
+        // Merge flintcli extension actions into their own per-tick timeline
+        let mut ext_timeline: BTreeMap<u32, Vec<(usize, &ExtEntry, usize)>> = BTreeMap::new();
+        for (test_idx, test_extras) in extras.iter().enumerate() {
+            for entry in &test_extras.actions {
+                for (value_idx, tick) in entry.at.iter().enumerate() {
+                    ext_timeline
+                        .entry(*tick)
+                        .or_default()
+                        .push((test_idx, entry, value_idx));
+                }
+            }
+        }
+        let max_tick = extras
+            .iter()
+            .map(SpecExtras::max_tick)
+            .fold(aggregate.max_tick, u32::max);
+
+        self.validate_positions(tests_with_offsets, extras, &aggregate)?;
+
+        // Last tick of every test: once it has passed, the test is complete and its
+        // results are kept as-is if the bot has to reconnect
+        let mut last_ticks: Vec<u32> = extras.iter().map(SpecExtras::max_tick).collect();
+        for (tick, entries) in &aggregate.timeline {
+            for (test_idx, ..) in entries {
+                last_ticks[*test_idx] = last_ticks[*test_idx].max(*tick);
+            }
+        }
+        let next_test = |tick: u32| {
+            last_ticks
+                .iter()
+                .position(|&last| last >= tick)
+                .unwrap_or(0)
+        };
+
+        // Suite hooks join every test's timeline: `before_each` counted from the test's
+        // start and run ahead of its own entries, `after_each` from its last tick and
+        // run after them
+        let suite = self.suite.take();
+        let mut before_each: BTreeMap<u32, Vec<(usize, &ExtEntry, usize)>> = BTreeMap::new();
+        let mut after_each: BTreeMap<u32, Vec<(usize, &ExtEntry, usize)>> = BTreeMap::new();
+        let mut max_tick = max_tick;
+        if let Some(ref suite) = suite {
+            for (test_idx, &last_tick) in last_ticks.iter().enumerate() {
+                for (hooks, timeline, start) in [
+                    (&suite.before_each, &mut before_each, 0),
+                    (&suite.after_each, &mut after_each, last_tick),
+                ] {
+                    for entry in hooks {
+                        for (value_idx, tick) in entry.at.iter().enumerate() {
+                            timeline
+                                .entry(start + tick)
+                                .or_default()
+                                .push((test_idx, entry, value_idx));
+                            max_tick = max_tick.max(start + tick + entry.within);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.status(format!("  Global timeline: {} ticks", max_tick));
+        self.status(format!(
             "  {} unique tick steps with actions",
             aggregate.unique_tick_count()
-        );
+        ));
         if !aggregate.breakpoints.is_empty() {
             let mut sorted_breakpoints: Vec<_> = aggregate.breakpoints.iter().collect();
             sorted_breakpoints.sort();
-            println!(
+            self.status(format!(
                 "  {} breakpoints at ticks: {:?}",
                 aggregate.breakpoints.len(),
                 sorted_breakpoints
-            );
+            ));
         }
         if break_after_setup {
-            println!("  {} Break after setup enabled", "→".yellow());
+            self.status(format!("  {} Break after setup enabled", "→".yellow()));
         }
-        println!();
+        self.status(String::new());
 
-        // Clean all test areas before starting
-        println!("{} Cleaning all test areas...", "→".blue());
-        for (test, offset) in tests_with_offsets.iter() {
-            let region = test.cleanup_region();
-            let world_min = self.apply_offset(region[0], *offset);
-            let world_max = self.apply_offset(region[1], *offset);
-            let cmd = format!(
-                "fill {} {} {} {} {} {} air",
-                world_min[0], world_min[1], world_min[2], world_max[0], world_max[1], world_max[2]
-            );
-            self.bot.send_command(&cmd).await?;
+        // Group nearby test areas so the bot can serve each group from one spot,
+        // standing just above the center of the group's first area
+        let area_tops: Vec<[i32; 3]> = tests_with_offsets
+            .iter()
+            .zip(&offsets)
+            .map(|((test, _), offset)| {
+                let region = test.cleanup_region();
+                self.apply_offset(
+                    [
+                        (region[0][0] + region[1][0]) / 2,
+                        region[0][1].max(region[1][1]) + 1,
+                        (region[0][2] + region[1][2]) / 2,
+                    ],
+                    *offset,
+                )
+            })
+            .collect();
+        let partitions = layout::partition_areas(&area_tops, layout::TELEPORT_RADIUS);
+        let stand_points: Vec<[i32; 3]> = partitions
+            .iter()
+            .map(|partition| area_tops[partitions.iter().position(|p| p == partition).unwrap_or(0)])
+            .collect();
+        self.bot_position = None;
+
+        let world_regions: Vec<Vec<[[i32; 3]; 2]>> = tests_with_offsets
+            .iter()
+            .zip(&offsets)
+            .zip(extras)
+            .map(|(((test, _), offset), test_extras)| {
+                test_extras
+                    .cleanup_regions(test)
+                    .iter()
+                    .map(|region| {
+                        [
+                            self.apply_offset(region[0], *offset),
+                            self.apply_offset(region[1], *offset),
+                        ]
+                    })
+                    .collect()
+            })
+            .collect();
+        self.check_overlaps(tests_with_offsets, &world_regions)?;
+        self.dirty_regions = world_regions
+            .iter()
+            .zip(&stand_points)
+            .zip(extras)
+            .flat_map(|((regions, stand_point), test_extras)| {
+                regions
+                    .iter()
+                    .map(move |region| (*stand_point, *region, test_extras.cleanup_fill.clone()))
+            })
+            .collect();
+
+        // Commands sent into unloaded chunks are silently dropped, so wait for
+        // the chunk of each test's first action before touching the world
+        self.status(format!("{} Waiting for test chunks to load...", "→".blue()));
+        let timeout = std::time::Duration::from_millis(self.world_load_timeout_ms);
+        let first_positions = first_action_positions(&aggregate, tests_with_offsets.len());
+        for (test_idx, (((test, _), offset), first_pos)) in tests_with_offsets
+            .iter()
+            .zip(&offsets)
+            .zip(first_positions)
+            .enumerate()
+        {
+            self.approach(stand_points[test_idx]).await?;
+            let pos = first_pos.unwrap_or(test.cleanup_region()[0]);
+            self.bot
+                .wait_for_chunk(self.apply_offset(pos, *offset), timeout)
+                .await?;
+        }
+
+        // Suite setup runs before the test areas are cleaned, so a shared
+        // scaffold must lie outside every test's cleanup region
+        if let Some(ref suite) = suite {
+            self.run_suite_phase("setup", &suite.setup, suite.offset)
+                .await?;
+        }
+
+        // Clean all test areas before starting, or put back their tick-0 copies on a replay
+        if self.replaying {
+            self.status(format!(
+                "{} Restoring test areas from the snapshot...",
+                "→".blue()
+            ));
+            self.restore_stash().await?;
+        } else {
+            self.status(format!("{} Cleaning all test areas...", "→".blue()));
+            let all_tests: Vec<usize> = (0..tests_with_offsets.len()).collect();
+            self.clean_tests(
+                &all_tests,
+                &world_regions,
+                extras,
+                &partitions,
+                &stand_points,
+            )
+            .await?;
+            if self.stash_enabled {
+                self.clear_snapshot().await?;
+                let regions = world_regions
+                    .iter()
+                    .zip(&stand_points)
+                    .flat_map(|(regions, stand_point)| {
+                        regions.iter().map(move |region| (*stand_point, *region))
+                    })
+                    .collect();
+                self.save_stash(regions).await?;
+            }
         }
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
 
+        // Random sequences are reset after cleaning, so every run starts from the same state
+        self.pin_randomness().await?;
+
         // Freeze time globally
-        self.bot.send_command("tick freeze").await?;
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        self.freeze_time().await?;
+
+        // Game time baseline for drift detection; pointless without frozen time
+        let drift_base = if self.real_time {
+            None
+        } else {
+            self.query_game_time()
+                .await?
+                .map(|game_time| (game_time, self.stats.ticks))
+        };
+        let mut last_drift_check = self.stats.ticks;
 
         // Break after setup if requested
         let mut stepping_mode = false;
@@ -306,40 +1710,265 @@ impl TestExecutor {
         }
 
         // Track results per test
-        let mut test_results: Vec<(usize, usize)> = vec![(0, 0); tests_with_offsets.len()]; // (passed, failed)
+        let mut test_results = vec![TestOutcome::default(); tests_with_offsets.len()];
+
+        // Failed assertions with a `within` tolerance, re-checked every tick
+        // as (test_idx, entry, value_idx, scheduled tick)
+        let mut retries: Vec<(usize, &ExtEntry, usize, u32)> = Vec::new();
+
+        // The earliest stop tick of any test halts the whole run, since every area keeps
+        // changing as long as ticks are stepped
+        let stop_tick = extras
+            .iter()
+            .filter_map(|test_extras| test_extras.stop_at_tick.or(self.stop_at_tick))
+            .min()
+            .filter(|stop| *stop < max_tick);
+        let max_tick = stop_tick.unwrap_or(max_tick);
+
+        if self.quiet {
+            let progress = ProgressBar::new(max_tick as u64 + 1);
+            progress.set_style(
+                ProgressStyle::with_template("  {bar:40.cyan/blue} tick {pos}/{len} {msg}")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            self.progress = Some(progress);
+        }
 
         // Execute merged timeline
         let mut current_tick = 0;
-        while current_tick <= aggregate.max_tick {
+        let mut replayed_tick = None;
+        let timeline_start = std::time::Instant::now();
+        let mut last_heartbeat = timeline_start;
+        while current_tick <= max_tick {
+            self.timeline_position = Some((current_tick, max_tick));
+            if self.heartbeat && last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+                let remaining = last_ticks
+                    .iter()
+                    .zip(&test_results)
+                    .filter(|(last, outcome)| {
+                        **last >= current_tick && outcome.aborted_at.is_none()
+                    })
+                    .count();
+                self.log_always(format!(
+                    "  {} Still running: tick {}/{}, {} of {} tests remaining, {}s elapsed",
+                    "ℹ".cyan(),
+                    current_tick,
+                    max_tick,
+                    remaining,
+                    tests_with_offsets.len(),
+                    timeline_start.elapsed().as_secs()
+                ));
+                last_heartbeat = std::time::Instant::now();
+            }
+            if !self.bot.is_connected() {
+                self.recover_connection(current_tick, next_test(current_tick))
+                    .await?;
+            }
+
+            // In-memory checkpoint of everything before this tick, restored if the
+            // connection drops part-way through it
+            let checkpoint = (test_results.clone(), retries.clone());
+
+            // Every entry due this tick, ordered by phase so all world changes land
+            // before any clone and every check sees the world after both. Within a
+            // phase entries are grouped by partition so the bot teleports at most once
+            // per group; order within a test is preserved
+            let mut scheduled: Vec<(Phase, usize, TickEntry, usize)> = Vec::new();
+            for (test_idx, entry, value_idx, scheduled_tick) in std::mem::take(&mut retries) {
+                scheduled.push((
+                    Phase::Check,
+                    test_idx,
+                    TickEntry::Retry(entry, scheduled_tick),
+                    value_idx,
+                ));
+            }
+            scheduled.extend(before_each.get(&current_tick).into_iter().flatten().map(
+                |(test_idx, entry, value_idx)| {
+                    (
+                        entry.action.phase(),
+                        *test_idx,
+                        TickEntry::Ext(entry),
+                        *value_idx,
+                    )
+                },
+            ));
             if let Some(entries) = aggregate.timeline.get(&current_tick) {
                 for (test_idx, entry, value_idx) in entries {
-                    let (test, offset) = &tests_with_offsets[*test_idx];
+                    scheduled.push((
+                        action_phase(&entry.action_type),
+                        *test_idx,
+                        TickEntry::Core(entry),
+                        *value_idx,
+                    ));
+                }
+            }
+            let ext_entries = [&ext_timeline, &after_each]
+                .into_iter()
+                .filter_map(|timeline| timeline.get(&current_tick))
+                .flatten();
+            for (test_idx, entry, value_idx) in ext_entries {
+                scheduled.push((
+                    entry.action.phase(),
+                    *test_idx,
+                    TickEntry::Ext(entry),
+                    *value_idx,
+                ));
+            }
+            order_due_entries(&mut scheduled, |(phase, test_idx, ..)| {
+                (*phase, partitions[*test_idx])
+            });
 
-                    match self
-                        .execute_action(current_tick, entry, *value_idx, *offset)
-                        .await
-                    {
-                        Ok(true) => {
-                            test_results[*test_idx].0 += 1; // increment passed
-                        }
-                        Ok(false) => {
-                            // Non-assertion action
+            let mut burst = 0;
+            for (phase, test_idx, entry, value_idx) in scheduled {
+                // Game time is frozen, so pausing between bursts only costs wall-clock time
+                if phase != Phase::Check
+                    && let Some(max_burst) = self.max_burst
+                {
+                    if burst == max_burst {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(BURST_PAUSE_MS))
+                            .await;
+                        burst = 0;
+                    }
+                    burst += 1;
+                }
+
+                // The rest of an aborted test's timeline is meaningless
+                if test_results[test_idx].aborted_at.is_some() {
+                    continue;
+                }
+
+                let (test, _) = &tests_with_offsets[test_idx];
+                let offset = &offsets[test_idx];
+                self.bot
+                    .set_trace_context(Some(current_tick), Some(&test.name));
+                match entry {
+                    TickEntry::Core(entry) => {
+                        self.approach(stand_points[test_idx]).await?;
+                        let outcome = self
+                            .execute_action(current_tick, &entry.action_type, value_idx, *offset)
+                            .await;
+                        if phase == Phase::Check {
+                            test_results[test_idx]
+                                .assertion_log
+                                .push(AssertionResult::new(
+                                    &test.name,
+                                    current_tick,
+                                    action_positions(&entry.action_type).first().copied(),
+                                    action_summary(&entry.action_type),
+                                    &outcome,
+                                ));
                         }
-                        Err(e) => {
-                            test_results[*test_idx].1 += 1; // increment failed
-                            println!(
-                                "    {} [{}] Tick {}: {}",
-                                "✗".red().bold(),
+                        self.record_outcome(
+                            outcome,
+                            &mut test_results[test_idx],
+                            &test.name,
+                            current_tick,
+                            phase,
+                            extras[test_idx].core_source(test, entry),
+                            action_summary(&entry.action_type),
+                        );
+                    }
+
+                    // Re-check of a tolerant assertion that failed at an earlier tick
+                    TickEntry::Retry(entry, scheduled_tick) => {
+                        self.approach(stand_points[test_idx]).await?;
+                        let outcome = self
+                            .execute_entry(current_tick, &test.name, entry, value_idx, *offset)
+                            .await;
+                        match outcome {
+                            Err(_) if current_tick < scheduled_tick + entry.within => {
+                                retries.push((test_idx, entry, value_idx, scheduled_tick));
+                                continue;
+                            }
+                            Ok(_) => self.log_action(format!(
+                                "    {} [{}] Tick {}: assertion from tick {} satisfied {} tick(s) late",
+                                "✓".green(),
                                 test.name,
                                 current_tick,
-                                e.to_string().red()
-                            );
+                                scheduled_tick,
+                                current_tick - scheduled_tick
+                            )),
+                            Err(_) => {}
                         }
+                        let outcome = outcome.map_err(|e| {
+                            anyhow::anyhow!(
+                                "{} (still failing after {} extra ticks)",
+                                e,
+                                entry.within
+                            )
+                        });
+                        self.finish_entry(
+                            outcome,
+                            &mut test_results[test_idx],
+                            &test.name,
+                            entry,
+                            scheduled_tick,
+                        );
                     }
-                }
-            }
 
-            // Check for breakpoint at end of this tick (before stepping)
+                    TickEntry::Ext(entry) => {
+                        let counts = &mut test_results[test_idx];
+
+                        // An assertion that has not run yet counts as not passed
+                        if let Some(required) = &entry.requires
+                            && counts.assertions.get(required) != Some(&true)
+                        {
+                            counts.skipped += 1;
+                            self.log_always(format!(
+                                "    {} [{}] Tick {}: skipped, requires '{}' which did not pass",
+                                "⊘".yellow(),
+                                test.name,
+                                current_tick,
+                                required
+                            ));
+                            continue;
+                        }
+
+                        self.approach(stand_points[test_idx]).await?;
+                        let outcome = self
+                            .execute_entry(current_tick, &test.name, entry, value_idx, *offset)
+                            .await;
+                        if outcome.is_err() && entry.within > 0 {
+                            self.log_action(format!(
+                                "    {} [{}] Tick {}: assertion not met yet, re-checking for up to {} ticks",
+                                "→".yellow(),
+                                test.name,
+                                current_tick,
+                                entry.within
+                            ));
+                            retries.push((test_idx, entry, value_idx, current_tick));
+                            continue;
+                        }
+                        self.finish_entry(
+                            outcome,
+                            &mut test_results[test_idx],
+                            &test.name,
+                            entry,
+                            current_tick,
+                        );
+                    }
+                }
+            }
+
+            // A tick cut short by a disconnect is replayed once from the checkpoint,
+            // so its failures are not blamed on the tests
+            if !self.bot.is_connected() {
+                if replayed_tick == Some(current_tick) {
+                    anyhow::bail!(
+                        "Lost connection to server again while replaying tick {}",
+                        current_tick
+                    );
+                }
+                replayed_tick = Some(current_tick);
+                (test_results, retries) = checkpoint;
+                self.recover_connection(current_tick, next_test(current_tick))
+                    .await?;
+                continue;
+            }
+
+            self.bot.set_trace_context(Some(current_tick), None);
+
+            // Check for breakpoint at end of this tick (before stepping)
             // Or if we're in stepping mode, break at every tick
             if aggregate.breakpoints.contains(&current_tick) || stepping_mode {
                 let should_continue = self
@@ -352,24 +1981,35 @@ impl TestExecutor {
             }
 
             // Advance to next tick (step or sprint depending on mode)
-            if current_tick < aggregate.max_tick {
+            if current_tick < max_tick {
                 if stepping_mode {
                     // In stepping mode, only advance one tick at a time
                     self.sprint_ticks(1).await?;
-                    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+                    tokio::time::sleep(tokio::time::Duration::from_millis(self.tick_delay_ms))
+                        .await;
                     current_tick += 1;
                 } else {
                     // In continue mode, sprint to next event or breakpoint
                     // Use the aggregate's helper method to find the next event
+                    let next_ext_tick = [&ext_timeline, &before_each, &after_each]
+                        .into_iter()
+                        .filter_map(|timeline| timeline.range(current_tick + 1..).next())
+                        .map(|(tick, _)| *tick)
+                        .min();
+                    let next_retry_tick = (!retries.is_empty()).then_some(current_tick + 1);
                     let next_event_tick = aggregate
                         .next_event_tick(current_tick)
-                        .unwrap_or(aggregate.max_tick + 1);
+                        .into_iter()
+                        .chain(next_ext_tick)
+                        .chain(next_retry_tick)
+                        .min()
+                        .unwrap_or(max_tick + 1);
 
                     // Calculate how many ticks to sprint
-                    let ticks_to_sprint = if next_event_tick <= aggregate.max_tick {
+                    let ticks_to_sprint = if next_event_tick <= max_tick {
                         next_event_tick - current_tick
                     } else {
-                        aggregate.max_tick - current_tick
+                        max_tick - current_tick
                     };
 
                     // Sprint the ticks
@@ -379,8 +2019,8 @@ impl TestExecutor {
                         0
                     };
 
-                    // Use sprint timing for retry delay (ensure at least 200ms)
-                    let retry_delay = sprint_time_ms.max(200);
+                    // Use sprint timing for retry delay (ensure at least the tick delay)
+                    let retry_delay = sprint_time_ms.max(self.tick_delay_ms);
                     tokio::time::sleep(tokio::time::Duration::from_millis(retry_delay)).await;
 
                     current_tick += ticks_to_sprint;
@@ -388,59 +2028,232 @@ impl TestExecutor {
             } else {
                 current_tick += 1;
             }
-        }
 
-        // Unfreeze time
-        self.bot.send_command("tick unfreeze").await?;
+            if let Some(ref progress) = self.progress {
+                progress.set_position(current_tick as u64);
+            }
 
-        // Clean all test areas after completion
-        println!("\n{} Cleaning up all test areas...", "→".blue());
-        for (test, offset) in tests_with_offsets.iter() {
-            let region = test.cleanup_region();
-            let world_min = self.apply_offset(region[0], *offset);
-            let world_max = self.apply_offset(region[1], *offset);
-            let cmd = format!(
-                "fill {} {} {} {} {} {} air",
-                world_min[0], world_min[1], world_min[2], world_max[0], world_max[1], world_max[2]
-            );
-            self.bot.send_command(&cmd).await?;
+            if self.stats.ticks - last_drift_check >= DRIFT_CHECK_INTERVAL {
+                self.check_drift(drift_base).await?;
+                last_drift_check = self.stats.ticks;
+            }
         }
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        self.bot.set_trace_context(None, None);
+        self.check_drift(drift_base).await?;
+
+        if let Some(progress) = self.progress.take() {
+            progress.finish_and_clear();
+        }
+
+        // Unfreeze time
+        self.unfreeze_time().await?;
 
         // Build results
         let results: Vec<TestResult> = tests_with_offsets
             .iter()
             .enumerate()
             .map(|(idx, (test, _))| {
-                let (passed, failed) = test_results[idx];
-                let success = failed == 0;
+                let TestOutcome {
+                    passed,
+                    failed,
+                    setup_errors,
+                    aborted_at,
+                    skipped,
+                    ref failures,
+                    ..
+                } = test_results[idx];
+                let min_pass_ratio = extras[idx].min_pass_ratio;
+                let checks_passed =
+                    setup_errors == 0 && meets_pass_ratio(passed, failed, min_pass_ratio);
+                let expect_fail = extras[idx].expect_fail;
+                // A test cut short by the stop tick never ran its later entries
+                let stopped = stop_tick.filter(|stop| last_ticks[idx] > *stop);
+                let success = checks_passed != expect_fail && stopped.is_none();
+                let mut skipped_note = if skipped > 0 {
+                    format!(", {} skipped", skipped)
+                } else {
+                    String::new()
+                };
+                if setup_errors > 0 {
+                    skipped_note.push_str(&format!(", {} setup errors", setup_errors));
+                }
+                if let Some(tick) = aborted_at {
+                    skipped_note.push_str(&format!(", aborted at tick {}", tick));
+                }
+                if let Some(stop) = stopped {
+                    skipped_note.push_str(&format!(
+                        ", stopped at tick {} of {}",
+                        stop, last_ticks[idx]
+                    ));
+                }
 
-                println!();
-                if success {
-                    println!(
-                        "  {} [{}] Test passed: {} assertions",
+                self.status(String::new());
+                let ratio_note = match min_pass_ratio {
+                    Some(min) if passed + failed > 0 => format!(
+                        " ({:.0}% passed, {:.0}% required)",
+                        100.0 * passed as f32 / (passed + failed) as f32,
+                        100.0 * min
+                    ),
+                    _ => String::new(),
+                };
+                if expect_fail && success {
+                    self.status(format!(
+                        "  {} [{}] Expected failure: {} passed, {} failed{}{}",
                         "✓".green().bold(),
                         test.name,
-                        passed
-                    );
+                        passed,
+                        failed,
+                        skipped_note,
+                        ratio_note
+                    ));
+                } else if expect_fail && checks_passed && stopped.is_none() {
+                    self.status(format!(
+                        "  {} [{}] Unexpected pass: {} passed, {} failed{}{} (marked expect_fail)",
+                        "✗".red().bold(),
+                        test.name,
+                        passed,
+                        failed,
+                        skipped_note,
+                        ratio_note
+                    ));
+                } else if success && failed == 0 {
+                    self.status(format!(
+                        "  {} [{}] Test passed: {} assertions{}{}",
+                        "✓".green().bold(),
+                        test.name,
+                        passed,
+                        skipped_note,
+                        ratio_note
+                    ));
+                } else if success {
+                    self.status(format!(
+                        "  {} [{}] Test passed: {} passed, {} failed{}{}",
+                        "✓".green().bold(),
+                        test.name,
+                        passed,
+                        failed,
+                        skipped_note,
+                        ratio_note
+                    ));
                 } else {
-                    println!(
-                        "  {} [{}] Test failed: {} passed, {} failed",
+                    self.status(format!(
+                        "  {} [{}] Test failed: {} passed, {} failed{}{}",
                         "✗".red().bold(),
                         test.name,
                         passed,
-                        failed
-                    );
+                        failed,
+                        skipped_note,
+                        ratio_note
+                    ));
                 }
 
-                if success {
+                if expect_fail && checks_passed && stopped.is_none() {
+                    TestResult::new(test.name.clone()).with_failure_reason(format!(
+                        "Unexpected pass: {} assertions passed, but the test is marked expect_fail\nRemove expect_fail if the test is fixed",
+                        passed
+                    ))
+                } else if success && !expect_fail {
                     TestResult::new(test.name.clone())
                 } else {
-                    TestResult::new(test.name.clone())
-                        .with_failure_reason(format!("{} assertions failed", failed))
+                    // First line is the summary, one line per failure follows
+                    let mut reason = match setup_errors {
+                        0 => format!("{} assertions failed", failed),
+                        _ => format!(
+                            "{} assertions failed, {} setup errors",
+                            failed, setup_errors
+                        ),
+                    };
+                    if let Some(stop) = stopped {
+                        reason = format!(
+                            "Stopped at tick {} before the test's last tick {}, {}",
+                            stop, last_ticks[idx], reason
+                        );
+                    }
+                    for failure in failures {
+                        reason.push('\n');
+                        reason.push_str(failure);
+                    }
+                    // An expected failure keeps its reason for reports, but passes
+                    let mut result = TestResult::new(test.name.clone()).with_failure_reason(reason);
+                    result.success = success;
+                    result
                 }
             })
             .collect();
+        self.assertion_results = test_results
+            .into_iter()
+            .flat_map(|outcome| outcome.assertion_log)
+            .collect();
+
+        // A stopped run leaves everything as it was at the stop tick for inspection,
+        // so neither the cleanup nor the suite teardown runs
+        if let Some(stop) = stop_tick {
+            self.stats.stopped_at = Some(stop);
+            self.log_always(format!(
+                "\n{} Stopped at the end of tick {}; time is unfrozen and the test areas are left intact:",
+                "⚠".yellow().bold(),
+                stop
+            ));
+            for ((test, _), regions) in tests_with_offsets.iter().zip(&world_regions) {
+                for [world_min, world_max] in regions {
+                    self.log_always(format!(
+                        "  {} [{}] Test area: [{}, {}, {}] to [{}, {}, {}]",
+                        "→".yellow(),
+                        test.name,
+                        world_min[0],
+                        world_min[1],
+                        world_min[2],
+                        world_max[0],
+                        world_max[1],
+                        world_max[2]
+                    ));
+                }
+            }
+            self.dirty_regions.clear();
+            self.suite = suite;
+            self.stats.duration = run_start.elapsed();
+            return Ok(results);
+        }
+
+        // Clean test areas after completion, keeping failed ones for inspection if requested
+        self.status(format!("\n{} Cleaning up test areas...", "→".blue()));
+        let mut to_clean = Vec::new();
+        for (test_idx, ((test, _), result)) in tests_with_offsets.iter().zip(&results).enumerate() {
+            if self.keep_on_failure && !result.success {
+                for [world_min, world_max] in &world_regions[test_idx] {
+                    self.status(format!(
+                        "  {} [{}] Kept failed test area: [{}, {}, {}] to [{}, {}, {}]",
+                        "→".yellow(),
+                        test.name,
+                        world_min[0],
+                        world_min[1],
+                        world_min[2],
+                        world_max[0],
+                        world_max[1],
+                        world_max[2]
+                    ));
+                }
+                continue;
+            }
+            to_clean.push(test_idx);
+        }
+        self.clean_tests(
+            &to_clean,
+            &world_regions,
+            extras,
+            &partitions,
+            &stand_points,
+        )
+        .await?;
+        self.dirty_regions.clear();
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+        // Suite teardown runs after the final cleanup
+        if let Some(ref suite) = suite {
+            self.run_suite_phase("teardown", &suite.teardown, suite.offset)
+                .await?;
+        }
+        self.suite = suite;
 
         // Send test results summary to chat
         let total_passed = results.iter().filter(|r| r.success).count();
@@ -462,77 +2275,1235 @@ impl TestExecutor {
             tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
         }
 
-        // Give messages time to be sent before potential disconnect
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        // Give messages time to be sent before potential disconnect
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+        self.stats.duration = run_start.elapsed();
+        Ok(results)
+    }
+
+    /// Check that every position a test touches lies inside its cleanup bounding box
+    /// Positions outside it read or leave behind uncleaned world state. Reported as
+    /// warnings, or as an error in strict mode.
+    fn validate_positions(
+        &self,
+        tests_with_offsets: &[(TestSpec, [i32; 3])],
+        extras: &[SpecExtras],
+        aggregate: &TimelineAggregate,
+    ) -> Result<()> {
+        let mut positions: Vec<BTreeSet<[i32; 3]>> =
+            vec![BTreeSet::new(); tests_with_offsets.len()];
+        for entries in aggregate.timeline.values() {
+            for (test_idx, entry, _) in entries {
+                positions[*test_idx].extend(action_positions(&entry.action_type));
+            }
+        }
+        for (test_idx, test_extras) in extras.iter().enumerate() {
+            for entry in &test_extras.actions {
+                positions[test_idx].extend(entry.action.positions());
+            }
+        }
+
+        let mut invalid = 0;
+        for ((test, _), test_positions) in tests_with_offsets.iter().zip(&positions) {
+            let region = test.cleanup_region();
+            let outside: Vec<String> = test_positions
+                .iter()
+                .filter(|pos| {
+                    (0..3).any(|axis| {
+                        pos[axis] < region[0][axis].min(region[1][axis])
+                            || pos[axis] > region[0][axis].max(region[1][axis])
+                    })
+                })
+                .map(|pos| format!("[{}, {}, {}]", pos[0], pos[1], pos[2]))
+                .collect();
+
+            if !outside.is_empty() {
+                invalid += 1;
+                let label = if self.strict {
+                    "Error:".red().bold()
+                } else {
+                    "Warning:".yellow().bold()
+                };
+                self.status(format!(
+                    "  {} [{}] Positions outside cleanup region: {}",
+                    label,
+                    test.name,
+                    outside.join(", ")
+                ));
+            }
+        }
+
+        if self.strict && invalid > 0 {
+            anyhow::bail!(
+                "{} test(s) use positions outside their cleanup region",
+                invalid
+            );
+        }
+        Ok(())
+    }
+
+    /// Fail the run if the world-space areas of two tests overlap
+    /// Overlapping tests clean and edit each other's blocks, so their results can't be
+    /// trusted; the automatic layout never produces them, but hand-picked offsets can.
+    fn check_overlaps(
+        &self,
+        tests_with_offsets: &[(TestSpec, [i32; 3])],
+        world_regions: &[Vec<[[i32; 3]; 2]>],
+    ) -> Result<()> {
+        let overlaps = layout::overlapping_areas(world_regions);
+        for (a, b, [min, max]) in &overlaps {
+            self.log_always(format!(
+                "  {} Test areas of [{}] and [{}] overlap at [{}, {}, {}] to [{}, {}, {}]",
+                "Error:".red().bold(),
+                tests_with_offsets[*a].0.name,
+                tests_with_offsets[*b].0.name,
+                min[0],
+                min[1],
+                min[2],
+                max[0],
+                max[1],
+                max[2]
+            ));
+        }
+        if !overlaps.is_empty() {
+            anyhow::bail!(
+                "{} pair(s) of tests have overlapping areas; give them offsets that keep their cleanup regions apart",
+                overlaps.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Count an action outcome toward a test's totals
+    fn record_outcome(
+        &self,
+        outcome: Result<bool>,
+        counts: &mut TestOutcome,
+        test_name: &str,
+        tick: u32,
+        phase: Phase,
+        source: Option<&SourceLocation>,
+        summary: String,
+    ) {
+        // The entries that led up to a test's first failure
+        if self.verbose_failures
+            && outcome.is_err()
+            && counts.failed == 0
+            && counts.setup_errors == 0
+        {
+            let mut context = format!(
+                "    {} [{}] Ran before the first failure:",
+                "ℹ".cyan(),
+                test_name
+            );
+            if counts.history.is_empty() {
+                context.push_str(" nothing");
+            }
+            for line in &counts.history {
+                context.push_str(&format!("\n      {}", line.dimmed()));
+            }
+            self.log_always(context);
+        }
+        if counts.history.len() == FAILURE_CONTEXT_ENTRIES {
+            counts.history.pop_front();
+        }
+        counts
+            .history
+            .push_back(format!("Tick {}: {}", tick, summary));
+
+        match outcome {
+            Ok(true) => {
+                counts.passed += 1;
+            }
+            Ok(false) => {
+                // Non-assertion action
+            }
+            Err(e) => {
+                let setup_error = phase != Phase::Check;
+                if setup_error {
+                    counts.setup_errors += 1;
+                    counts
+                        .failures
+                        .push(format!("Tick {}: setup error: {}", tick, e));
+                } else {
+                    counts.failed += 1;
+                    counts.failures.push(format!("Tick {}: {}", tick, e));
+                }
+                self.log_always(format!(
+                    "    {} [{}] Tick {}: {}",
+                    "✗".red().bold(),
+                    test_name,
+                    tick,
+                    e.to_string().red()
+                ));
+                if self.editor_output {
+                    let location = source
+                        .map(|source| {
+                            format!("file:{} line:{} ", source.file.display(), source.line)
+                        })
+                        .unwrap_or_default();
+                    self.log_always(format!("FAIL {}test:{} tick:{}", location, test_name, tick));
+                }
+                if setup_error && self.abort_on_setup_error {
+                    counts.aborted_at = Some(tick);
+                    self.log_always(format!(
+                        "    {} [{}] Tick {}: setup failed, skipping the rest of the test",
+                        "⚠".yellow().bold(),
+                        test_name,
+                        tick
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Run a flintcli-handled timeline entry, `repeat` times in a row
+    async fn execute_entry(
+        &mut self,
+        tick: u32,
+        test_name: &str,
+        entry: &ExtEntry,
+        value_idx: usize,
+        offset: [i32; 3],
+    ) -> Result<bool> {
+        // An entry counts as an assertion if any repetition was one
+        let mut asserted = false;
+        for run in 1..=entry.repeat {
+            let outcome = match &entry.action {
+                EntryAction::Core(action) => {
+                    self.execute_action(tick, action, value_idx, offset).await
+                }
+                EntryAction::Ext(action) => {
+                    self.execute_ext_action(tick, test_name, action, value_idx, offset)
+                        .await
+                }
+            };
+            match outcome {
+                Ok(is_assertion) => asserted |= is_assertion,
+                Err(e) if entry.repeat > 1 => {
+                    anyhow::bail!("{} (repetition {}/{})", e, run, entry.repeat)
+                }
+                Err(e) => return Err(e),
+            }
+            if run < entry.repeat {
+                tokio::time::sleep(tokio::time::Duration::from_millis(REPEAT_DELAY_MS)).await;
+            }
+        }
+        Ok(asserted)
+    }
+
+    /// Record a flintcli-handled entry's final outcome, including its `id` for `requires`
+    fn finish_entry(
+        &self,
+        outcome: Result<bool>,
+        counts: &mut TestOutcome,
+        test_name: &str,
+        entry: &ExtEntry,
+        tick: u32,
+    ) {
+        if let Some(id) = &entry.id {
+            counts.assertions.insert(id.clone(), outcome.is_ok());
+        }
+        // Annotations like `log` run in the check phase but aren't assertions
+        if entry.action.phase() == Phase::Check && !matches!(outcome, Ok(false)) {
+            counts.assertion_log.push(AssertionResult::new(
+                test_name,
+                tick,
+                entry.action.positions().first().copied(),
+                entry.action.summary(),
+                &outcome,
+            ));
+        }
+        self.record_outcome(
+            outcome,
+            counts,
+            test_name,
+            tick,
+            entry.action.phase(),
+            entry.source.as_ref(),
+            entry.action.summary(),
+        );
+    }
+
+    /// Check that the block at a spec-local position matches one of the acceptable blocks,
+    /// leaving the states in `ignore` out of the comparison
+    async fn assert_block(
+        &mut self,
+        tick: u32,
+        pos: [i32; 3],
+        acceptable: &[String],
+        ignore: &[String],
+        offset: [i32; 3],
+    ) -> Result<()> {
+        let world_pos = self.apply_offset(pos, offset);
+        let expected = match acceptable {
+            [single] => single.clone(),
+            _ => format!("one of {}", acceptable.join(", ")),
+        };
+
+        // Block tags such as `#minecraft:logs` are tested by the server, which also knows
+        // the tags its datapacks add; it is already at the tick, so nothing has to settle
+        let (tags, blocks): (Vec<String>, Vec<String>) = acceptable
+            .iter()
+            .cloned()
+            .partition(|block| block.starts_with('#'));
+        for tag in &tags {
+            let in_tag = self
+                .check_condition(&format!(
+                    "if block {} {} {} {}",
+                    world_pos[0], world_pos[1], world_pos[2], tag
+                ))
+                .await?;
+            if in_tag {
+                self.log_action(format!(
+                    "    {} Tick {}: assert block at [{}, {}, {}] is {}",
+                    "✓".green(),
+                    tick,
+                    pos[0],
+                    pos[1],
+                    pos[2],
+                    tag.dimmed()
+                ));
+                return Ok(());
+            }
+        }
+        if blocks.is_empty() {
+            let actual_block = self.bot.get_block(world_pos).await?;
+            anyhow::bail!(
+                "Block at [{}, {}, {}] is not in {}\n{}",
+                pos[0],
+                pos[1],
+                pos[2],
+                tags.join(" or "),
+                format_diff(&expected, &actual_block.to_string())
+            );
+        }
+        let acceptable = blocks.as_slice();
+
+        // The update packet can trail the tick, e.g. for observers and repeaters
+        let (actual_block, waited) = self.settle_block(world_pos, acceptable, ignore).await?;
+
+        if acceptable
+            .iter()
+            .any(|expected| self.block_matches(expected, &actual_block, ignore))
+        {
+            self.log_action(format!(
+                "    {} Tick {}: assert block at [{}, {}, {}] is {}{}",
+                "✓".green(),
+                tick,
+                pos[0],
+                pos[1],
+                pos[2],
+                expected.dimmed(),
+                settle_note(waited)
+            ));
+            Ok(())
+        } else {
+            // Keep reading for a little longer so the message can say whether the
+            // block was merely late
+            let mut late_match = None;
+            for attempt in 1..=LATE_CHECK_ATTEMPTS {
+                tokio::time::sleep(tokio::time::Duration::from_millis(LATE_CHECK_DELAY_MS)).await;
+                let block = self.bot.get_block(world_pos).await?;
+                if acceptable
+                    .iter()
+                    .any(|expected| self.block_matches(expected, &block, ignore))
+                {
+                    late_match = Some((block, u64::from(attempt) * LATE_CHECK_DELAY_MS));
+                    break;
+                }
+            }
+            let note = match late_match {
+                Some((block, late_ms)) => format!(
+                    "      note:     became {} about {}ms later; the update is slow, consider raising tick_delay_ms",
+                    block, late_ms
+                ),
+                None => format!(
+                    "      note:     still not matching {}ms later",
+                    u64::from(LATE_CHECK_ATTEMPTS) * LATE_CHECK_DELAY_MS
+                ),
+            };
+
+            // With a single expected block of the right id, name the states that differ
+            let states = match acceptable {
+                [single] => state_mismatches(
+                    &apply_alias(single, &self.block_aliases),
+                    &actual_block,
+                    ignore,
+                ),
+                _ => Vec::new(),
+            };
+            let states = if states.is_empty() {
+                String::new()
+            } else {
+                format!("      states:   {}\n", states.join(", "))
+            };
+
+            anyhow::bail!(
+                "Block mismatch at [{}, {}, {}]\n{}\n{}{}",
+                pos[0],
+                pos[1],
+                pos[2],
+                format_diff(&expected, &actual_block.to_string()),
+                states,
+                note
+            );
+        }
+    }
+
+    async fn execute_action(
+        &mut self,
+        tick: u32,
+        action: &ActionType,
+        value_idx: usize,
+        offset: [i32; 3],
+    ) -> Result<bool> {
+        match action {
+            ActionType::Place { pos, block } => {
+                let block = self.outgoing_block(block)?;
+                let world_pos = self.apply_offset(*pos, offset);
+                let cmd = format!(
+                    "setblock {} {} {} {}",
+                    world_pos[0], world_pos[1], world_pos[2], block
+                );
+                self.send_world_command(tick, &cmd).await?;
+                self.log_action(format!(
+                    "    {} Tick {}: place at [{}, {}, {}] = {}",
+                    "→".blue(),
+                    tick,
+                    pos[0],
+                    pos[1],
+                    pos[2],
+                    block.dimmed()
+                ));
+                Ok(false)
+            }
+
+            ActionType::PlaceEach { blocks } => {
+                let placements = blocks
+                    .iter()
+                    .map(|placement| Ok((placement.pos, self.outgoing_block(&placement.block)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                // Feedback is checked once for all blocks, so the placements stay paced
+                // by place_delay_ms alone
+                self.drain_chat().await;
+                for (pos, block) in &placements {
+                    let world_pos = self.apply_offset(*pos, offset);
+                    let cmd = format!(
+                        "setblock {} {} {} {}",
+                        world_pos[0], world_pos[1], world_pos[2], block
+                    );
+                    self.bot.send_command(&cmd).await?;
+                    self.log_action(format!(
+                        "    {} Tick {}: place at [{}, {}, {}] = {}",
+                        "→".blue(),
+                        tick,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        block.dimmed()
+                    ));
+                    if self.place_delay_ms > 0 {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(self.place_delay_ms))
+                            .await;
+                    }
+                }
+                if let Some(message) = self.command_rejection(blocks.len()).await {
+                    anyhow::bail!(
+                        "Server rejected a setblock of the place_each at tick {}: {}",
+                        tick,
+                        message
+                    );
+                }
+                Ok(false)
+            }
+
+            ActionType::Fill { region, with } => {
+                let with = self.outgoing_block(with)?;
+                let world_min = self.apply_offset(region[0], offset);
+                let world_max = self.apply_offset(region[1], offset);
+                let cmd = format!(
+                    "fill {} {} {} {} {} {} {}",
+                    world_min[0],
+                    world_min[1],
+                    world_min[2],
+                    world_max[0],
+                    world_max[1],
+                    world_max[2],
+                    with
+                );
+                self.send_world_command(tick, &cmd).await?;
+                self.log_action(format!(
+                    "    {} Tick {}: fill [{},{},{}] to [{},{},{}] = {}",
+                    "→".blue(),
+                    tick,
+                    region[0][0],
+                    region[0][1],
+                    region[0][2],
+                    region[1][0],
+                    region[1][1],
+                    region[1][2],
+                    with.dimmed()
+                ));
+                Ok(false)
+            }
+
+            ActionType::Remove { pos } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let cmd = format!(
+                    "setblock {} {} {} air",
+                    world_pos[0], world_pos[1], world_pos[2]
+                );
+                self.send_world_command(tick, &cmd).await?;
+                self.log_action(format!(
+                    "    {} Tick {}: remove at [{}, {}, {}]",
+                    "→".blue(),
+                    tick,
+                    pos[0],
+                    pos[1],
+                    pos[2]
+                ));
+                Ok(false)
+            }
+
+            ActionType::Assert { checks } => {
+                for check in checks {
+                    self.assert_block(
+                        tick,
+                        check.pos,
+                        std::slice::from_ref(&check.is),
+                        &[],
+                        offset,
+                    )
+                    .await?;
+                }
+                Ok(true)
+            }
+
+            ActionType::AssertState { pos, state, values } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let expected_value = &values[value_idx];
+
+                let (actual_value, waited) = self
+                    .settle_block_state(world_pos, state, |value| value == expected_value.as_str())
+                    .await?;
+
+                if actual_value.as_ref() == Some(expected_value) {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert block at [{}, {}, {}] state {} = {}{}",
+                        "✓".green(),
+                        tick,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        state.dimmed(),
+                        expected_value.dimmed(),
+                        settle_note(waited)
+                    ));
+                    Ok(true)
+                } else {
+                    anyhow::bail!(
+                        "State '{}' mismatch at [{}, {}, {}]\n{}",
+                        state,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        format_diff(
+                            expected_value,
+                            actual_value.as_deref().unwrap_or("<no such property>")
+                        )
+                    );
+                }
+            }
+        }
+    }
+
+    async fn execute_ext_action(
+        &mut self,
+        tick: u32,
+        test_name: &str,
+        action: &ExtAction,
+        value_idx: usize,
+        offset: [i32; 3],
+    ) -> Result<bool> {
+        match action {
+            ExtAction::AssertRegion {
+                region,
+                expect,
+                ignore_unspecified,
+            } => {
+                let expected: HashMap<[i32; 3], &str> =
+                    expect.iter().map(|p| (p.pos, p.block.as_str())).collect();
+                let min = [
+                    region[0][0].min(region[1][0]),
+                    region[0][1].min(region[1][1]),
+                    region[0][2].min(region[1][2]),
+                ];
+                let max = [
+                    region[0][0].max(region[1][0]),
+                    region[0][1].max(region[1][1]),
+                    region[0][2].max(region[1][2]),
+                ];
+
+                // One bulk read of the synced world instead of an await per block
+                let read_start = std::time::Instant::now();
+                let blocks = self.bot.snapshot_region(
+                    self.apply_offset(min, offset),
+                    self.apply_offset(max, offset),
+                )?;
+                tracing::debug!(
+                    "Read {} blocks of region in {:?}",
+                    blocks.len(),
+                    read_start.elapsed()
+                );
+
+                let mut checked = 0;
+                let mut mismatches = Vec::new();
+                for x in min[0]..=max[0] {
+                    for y in min[1]..=max[1] {
+                        for z in min[2]..=max[2] {
+                            let pos = [x, y, z];
+                            let expected_block = match expected.get(&pos) {
+                                Some(block) => *block,
+                                None if *ignore_unspecified => continue,
+                                None => "minecraft:air",
+                            };
+
+                            let actual_block = blocks.get(&self.apply_offset(pos, offset));
+                            checked += 1;
+
+                            if !actual_block
+                                .is_some_and(|b| self.block_matches(expected_block, b, &[]))
+                            {
+                                mismatches.push(format!(
+                                    "[{}, {}, {}] expected {}, actual {}",
+                                    x,
+                                    y,
+                                    z,
+                                    expected_block,
+                                    describe(actual_block)
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                if mismatches.is_empty() {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert region [{},{},{}] to [{},{},{}] matches ({} blocks)",
+                        "✓".green(),
+                        tick,
+                        min[0],
+                        min[1],
+                        min[2],
+                        max[0],
+                        max[1],
+                        max[2],
+                        checked
+                    ));
+                    Ok(true)
+                } else {
+                    let total = mismatches.len();
+                    mismatches.truncate(MAX_REPORTED_MISMATCHES);
+                    anyhow::bail!(
+                        "Region [{},{},{}] to [{},{},{}] has {} of {} blocks mismatched: {}",
+                        min[0],
+                        min[1],
+                        min[2],
+                        max[0],
+                        max[1],
+                        max[2],
+                        total,
+                        checked,
+                        mismatches.join("; ")
+                    );
+                }
+            }
+
+            ExtAction::AssertEmpty { region } => {
+                let min = [0, 1, 2].map(|axis| region[0][axis].min(region[1][axis]));
+                let max = [0, 1, 2].map(|axis| region[0][axis].max(region[1][axis]));
+                let blocks = self.bot.snapshot_region(
+                    self.apply_offset(min, offset),
+                    self.apply_offset(max, offset),
+                )?;
+
+                let mut checked = 0;
+                let mut occupied = Vec::new();
+                for x in min[0]..=max[0] {
+                    for y in min[1]..=max[1] {
+                        for z in min[2]..=max[2] {
+                            let block = blocks.get(&self.apply_offset([x, y, z], offset));
+                            checked += 1;
+                            if !block.is_some_and(is_air) {
+                                occupied.push(format!("[{}, {}, {}] {}", x, y, z, describe(block)));
+                            }
+                        }
+                    }
+                }
+
+                if occupied.is_empty() {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert region [{},{},{}] to [{},{},{}] is empty ({} blocks)",
+                        "✓".green(),
+                        tick,
+                        min[0],
+                        min[1],
+                        min[2],
+                        max[0],
+                        max[1],
+                        max[2],
+                        checked
+                    ));
+                    Ok(true)
+                } else {
+                    let total = occupied.len();
+                    occupied.truncate(MAX_REPORTED_MISMATCHES);
+                    anyhow::bail!(
+                        "Region [{},{},{}] to [{},{},{}] is not empty: {} of {} blocks are not air: {}{}",
+                        min[0],
+                        min[1],
+                        min[2],
+                        max[0],
+                        max[1],
+                        max[2],
+                        total,
+                        checked,
+                        occupied.join("; "),
+                        if total > MAX_REPORTED_MISMATCHES {
+                            "; ..."
+                        } else {
+                            ""
+                        }
+                    );
+                }
+            }
+
+            ExtAction::AssertStable { region, over_ticks } => {
+                let min = [0, 1, 2].map(|axis| region[0][axis].min(region[1][axis]));
+                let max = [0, 1, 2].map(|axis| region[0][axis].max(region[1][axis]));
+                let blocks = self.bot.snapshot_region(
+                    self.apply_offset(min, offset),
+                    self.apply_offset(max, offset),
+                )?;
+                let mut positions = Vec::new();
+                let mut current = Vec::new();
+                for x in min[0]..=max[0] {
+                    for y in min[1]..=max[1] {
+                        for z in min[2]..=max[2] {
+                            positions.push([x, y, z]);
+                            current
+                                .push(describe(blocks.get(&self.apply_offset([x, y, z], offset))));
+                        }
+                    }
+                }
+                let key = (test_name.to_string(), [min, max]);
+
+                if value_idx == 0 {
+                    self.log_action(format!(
+                        "    {} Tick {}: captured region [{},{},{}] to [{},{},{}] ({} blocks)",
+                        "→".blue(),
+                        tick,
+                        min[0],
+                        min[1],
+                        min[2],
+                        max[0],
+                        max[1],
+                        max[2],
+                        current.len()
+                    ));
+                    self.region_snapshots.insert(key, current);
+                    return Ok(false);
+                }
+
+                // A change is reported once, at the first tick it is seen
+                let Some(before) = self.region_snapshots.get(&key) else {
+                    return Ok(false);
+                };
+                let mut changed: Vec<String> = positions
+                    .iter()
+                    .zip(before.iter().zip(&current))
+                    .filter(|(_, (before, now))| before != now)
+                    .map(|(pos, (before, now))| {
+                        format!("[{}, {}, {}] {} -> {}", pos[0], pos[1], pos[2], before, now)
+                    })
+                    .collect();
+                if !changed.is_empty() {
+                    self.region_snapshots.remove(&key);
+                    let total = changed.len();
+                    changed.truncate(MAX_REPORTED_MISMATCHES);
+                    anyhow::bail!(
+                        "Region [{},{},{}] to [{},{},{}] changed {} tick(s) after it was captured: {} of {} blocks: {}{}",
+                        min[0],
+                        min[1],
+                        min[2],
+                        max[0],
+                        max[1],
+                        max[2],
+                        value_idx,
+                        total,
+                        current.len(),
+                        changed.join("; "),
+                        if total > MAX_REPORTED_MISMATCHES {
+                            "; ..."
+                        } else {
+                            ""
+                        }
+                    );
+                }
+                if value_idx < *over_ticks as usize {
+                    return Ok(false);
+                }
+
+                self.region_snapshots.remove(&key);
+                self.log_action(format!(
+                    "    {} Tick {}: region [{},{},{}] to [{},{},{}] stable over {} ticks ({} blocks)",
+                    "✓".green(),
+                    tick,
+                    min[0],
+                    min[1],
+                    min[2],
+                    max[0],
+                    max[1],
+                    max[2],
+                    over_ticks,
+                    current.len()
+                ));
+                Ok(true)
+            }
+
+            ExtAction::AssertStateRange { pos, state, range } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let [min, max] = *range;
+                let in_range = |value: &str| {
+                    value
+                        .parse::<i64>()
+                        .is_ok_and(|value| (min..=max).contains(&value))
+                };
+                let (actual_value, waited) =
+                    self.settle_block_state(world_pos, state, in_range).await?;
+
+                match actual_value {
+                    Some(actual) if in_range(&actual) => {
+                        self.log_action(format!(
+                            "    {} Tick {}: assert block at [{}, {}, {}] state {} = {} in {}..={}{}",
+                            "✓".green(),
+                            tick,
+                            pos[0],
+                            pos[1],
+                            pos[2],
+                            state.dimmed(),
+                            actual.dimmed(),
+                            min,
+                            max,
+                            settle_note(waited)
+                        ));
+                        Ok(true)
+                    }
+                    Some(actual) if actual.parse::<i64>().is_err() => anyhow::bail!(
+                        "State '{}' at [{}, {}, {}] is '{}', which is not a number (expected {}..={})",
+                        state,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        actual,
+                        min,
+                        max
+                    ),
+                    Some(actual) => anyhow::bail!(
+                        "State '{}' out of range at [{}, {}, {}]: {} not in {}..={}",
+                        state,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        actual,
+                        min,
+                        max
+                    ),
+                    None => {
+                        let block_id = self.bot.get_block_id(world_pos).await?;
+                        anyhow::bail!(
+                            "State '{}' missing at [{}, {}, {}] ({} has no such property, expected {}..={})",
+                            state,
+                            pos[0],
+                            pos[1],
+                            pos[2],
+                            block_id,
+                            min,
+                            max
+                        );
+                    }
+                }
+            }
+
+            ExtAction::AssertPower { pos, strength } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let Some(actual) = self.bot.get_block_property(world_pos, "power").await? else {
+                    let block_id = self.bot.get_block_id(world_pos).await?;
+                    anyhow::bail!(
+                        "Block at [{}, {}, {}] has no redstone power level ({} has no 'power' property)",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        block_id
+                    );
+                };
+
+                if actual == strength.to_string() {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert power at [{}, {}, {}] = {}",
+                        "✓".green(),
+                        tick,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        strength.to_string().dimmed()
+                    ));
+                    Ok(true)
+                } else {
+                    anyhow::bail!(
+                        "Power mismatch at [{}, {}, {}]\n{}",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        format_diff(&strength.to_string(), &actual)
+                    );
+                }
+            }
+
+            ExtAction::AssertPowerLine {
+                start,
+                direction,
+                length,
+                from_strength,
+            } => {
+                let step = direction_step(direction)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown direction '{}'", direction))?;
+                let line: Vec<([i32; 3], u8)> = (0..*length as i32)
+                    .map(|i| {
+                        (
+                            [0, 1, 2].map(|axis| start[axis] + step[axis] * i),
+                            from_strength.saturating_sub(i.min(15) as u8),
+                        )
+                    })
+                    .collect();
+                let end = line.last().map_or(*start, |(pos, _)| *pos);
+                let min = [0, 1, 2].map(|axis| start[axis].min(end[axis]));
+                let max = [0, 1, 2].map(|axis| start[axis].max(end[axis]));
+                let blocks = self.bot.snapshot_region(
+                    self.apply_offset(min, offset),
+                    self.apply_offset(max, offset),
+                )?;
+
+                let mismatched: Vec<(usize, [i32; 3], u8, String)> = line
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, (pos, expected))| {
+                        let block = blocks.get(&self.apply_offset(*pos, offset));
+                        let actual = block.and_then(|b| b.properties.get("power"));
+                        (actual != Some(&expected.to_string())).then(|| {
+                            let actual = match (block, actual) {
+                                (_, Some(power)) => power.clone(),
+                                (Some(block), None) => format!("no power level ({})", block),
+                                (None, None) => describe(None),
+                            };
+                            (i, *pos, *expected, actual)
+                        })
+                    })
+                    .collect();
+
+                match mismatched.first() {
+                    None => {
+                        self.log_action(format!(
+                            "    {} Tick {}: assert power line from [{}, {}, {}] {} x{} = {}..{}",
+                            "✓".green(),
+                            tick,
+                            start[0],
+                            start[1],
+                            start[2],
+                            direction,
+                            length,
+                            from_strength,
+                            line.last().map_or(*from_strength, |(_, power)| *power)
+                        ));
+                        Ok(true)
+                    }
+                    Some((i, pos, expected, actual)) => anyhow::bail!(
+                        "Power line from [{}, {}, {}] {} differs first at step {} [{}, {}, {}] ({} of {} blocks mismatched)\n{}",
+                        start[0],
+                        start[1],
+                        start[2],
+                        direction,
+                        i,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        mismatched.len(),
+                        length,
+                        format_diff(&expected.to_string(), actual)
+                    ),
+                }
+            }
+
+            ExtAction::Log { message } => {
+                self.log_action(format!(
+                    "    {} [{}] Tick {}: {}",
+                    "ℹ".cyan(),
+                    test_name,
+                    tick,
+                    message.italic()
+                ));
+                Ok(false)
+            }
+
+            ExtAction::AssertChanged { pos, from, to } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let actual = self.bot.get_block(world_pos).await?;
+                let key = (test_name.to_string(), world_pos);
+
+                if value_idx == 0 {
+                    if !self.block_matches(from, &actual, &[]) {
+                        anyhow::bail!(
+                            "Block at [{}, {}, {}] did not match before the change\n{}",
+                            pos[0],
+                            pos[1],
+                            pos[2],
+                            format_diff(from, &actual.to_string())
+                        );
+                    }
+                    self.log_action(format!(
+                        "    {} Tick {}: captured block at [{}, {}, {}] = {}",
+                        "→".blue(),
+                        tick,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        from.dimmed()
+                    ));
+                    self.snapshots.insert(key, actual.to_string());
+                    return Ok(false);
+                }
+
+                // A failed first snapshot was already reported
+                let Some(before) = self.snapshots.remove(&key) else {
+                    return Ok(false);
+                };
+                if !self.block_matches(to, &actual, &[]) {
+                    anyhow::bail!(
+                        "Block at [{}, {}, {}] did not change as expected\n      before:   {}\n{}",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        before,
+                        format_diff(to, &actual.to_string())
+                    );
+                }
+                self.log_action(format!(
+                    "    {} Tick {}: block at [{}, {}, {}] changed {} -> {}",
+                    "✓".green(),
+                    tick,
+                    pos[0],
+                    pos[1],
+                    pos[2],
+                    from.dimmed(),
+                    to.dimmed()
+                ));
+                Ok(true)
+            }
+
+            ExtAction::Clone { from, to } => {
+                let min = [0, 1, 2].map(|axis| from[0][axis].min(from[1][axis]));
+                let max = [0, 1, 2].map(|axis| from[0][axis].max(from[1][axis]));
+                let dest_max = [0, 1, 2].map(|axis| to[axis] + max[axis] - min[axis]);
+                let overlaps =
+                    (0..3).all(|axis| to[axis] <= max[axis] && min[axis] <= dest_max[axis]);
 
-        Ok(results)
-    }
+                // Vanilla refuses overlapping clones unless forced
+                let mode = if overlaps {
+                    self.log_always(format!(
+                        "    {} [{}] Tick {}: clone source and destination overlap, using force mode",
+                        "⚠".yellow().bold(),
+                        test_name,
+                        tick
+                    ));
+                    " replace force"
+                } else {
+                    ""
+                };
 
-    async fn execute_action(
-        &mut self,
-        tick: u32,
-        entry: &TimelineEntry,
-        value_idx: usize,
-        offset: [i32; 3],
-    ) -> Result<bool> {
-        match &entry.action_type {
-            ActionType::Place { pos, block } => {
-                let world_pos = self.apply_offset(*pos, offset);
+                let world_min = self.apply_offset(min, offset);
+                let world_max = self.apply_offset(max, offset);
+                let world_to = self.apply_offset(*to, offset);
                 let cmd = format!(
-                    "setblock {} {} {} {}",
-                    world_pos[0], world_pos[1], world_pos[2], block
+                    "clone {} {} {} {} {} {} {} {} {}{}",
+                    world_min[0],
+                    world_min[1],
+                    world_min[2],
+                    world_max[0],
+                    world_max[1],
+                    world_max[2],
+                    world_to[0],
+                    world_to[1],
+                    world_to[2],
+                    mode
                 );
                 self.bot.send_command(&cmd).await?;
-                println!(
-                    "    {} Tick {}: place at [{}, {}, {}] = {}",
+                self.log_action(format!(
+                    "    {} Tick {}: clone [{}, {}, {}] to [{}, {}, {}] -> [{}, {}, {}]",
                     "→".blue(),
                     tick,
-                    pos[0],
-                    pos[1],
-                    pos[2],
-                    block.dimmed()
-                );
+                    min[0],
+                    min[1],
+                    min[2],
+                    max[0],
+                    max[1],
+                    max[2],
+                    to[0],
+                    to[1],
+                    to[2]
+                ));
                 Ok(false)
             }
 
-            ActionType::PlaceEach { blocks } => {
-                for placement in blocks {
-                    let world_pos = self.apply_offset(placement.pos, offset);
-                    let cmd = format!(
-                        "setblock {} {} {} {}",
-                        world_pos[0], world_pos[1], world_pos[2], placement.block
+            ExtAction::AssertAny { checks } => {
+                for check in checks {
+                    self.assert_block(tick, check.pos, check.is.as_slice(), &check.ignore, offset)
+                        .await?;
+                }
+                Ok(true)
+            }
+
+            ExtAction::Command { raw } => {
+                let cmd = resolve_command(raw, offset)?;
+                self.bot.send_command(&cmd).await?;
+                self.log_action(format!(
+                    "    {} Tick {}: command /{}",
+                    "→".blue(),
+                    tick,
+                    cmd.dimmed()
+                ));
+                Ok(false)
+            }
+
+            ExtAction::AssertSign { pos, lines } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let actual = self.query_sign(*pos, world_pos).await?;
+                let mismatched: Vec<String> = lines
+                    .iter()
+                    .enumerate()
+                    .filter(|(line, expected)| {
+                        expected.as_deref().is_some_and(|expected| {
+                            actual.get(*line).map_or("", String::as_str) != expected
+                        })
+                    })
+                    .map(|(line, _)| (line + 1).to_string())
+                    .collect();
+
+                if mismatched.is_empty() {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert sign at [{}, {}, {}] ({} lines checked)",
+                        "✓".green(),
+                        tick,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        lines.iter().filter(|line| line.is_some()).count()
+                    ));
+                    Ok(true)
+                } else {
+                    // Unchecked lines show as `*`
+                    let expected = lines
+                        .iter()
+                        .map(|line| match line {
+                            Some(text) => format!("{:?}", text),
+                            None => "*".to_string(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" | ");
+                    let actual = actual
+                        .iter()
+                        .map(|text| format!("{:?}", text))
+                        .collect::<Vec<_>>()
+                        .join(" | ");
+                    anyhow::bail!(
+                        "Sign text mismatch at [{}, {}, {}] on line {}\n{}",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        mismatched.join(", "),
+                        format_diff(&expected, &actual)
                     );
-                    self.bot.send_command(&cmd).await?;
-                    println!(
-                        "    {} Tick {}: place at [{}, {}, {}] = {}",
-                        "→".blue(),
+                }
+            }
+
+            ExtAction::AssertContainer { pos, item, count } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let items = self.query_items(*pos, world_pos).await?;
+                let actual: u32 = items
+                    .iter()
+                    .filter(|(id, _)| item.as_deref().is_none_or(|item| item_matches(item, id)))
+                    .map(|(_, stack)| *stack)
+                    .sum();
+                let what = item.as_deref().unwrap_or("items");
+
+                if actual == *count {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert container at [{}, {}, {}] holds {} {}",
+                        "✓".green(),
                         tick,
-                        placement.pos[0],
-                        placement.pos[1],
-                        placement.pos[2],
-                        placement.block.dimmed()
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        count,
+                        what.dimmed()
+                    ));
+                    Ok(true)
+                } else {
+                    anyhow::bail!(
+                        "Container mismatch at [{}, {}, {}]\n{}",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        format_diff(
+                            &format!("{} {}", count, what),
+                            &format!("{} {} (contents: {})", actual, what, describe_items(&items))
+                        )
                     );
-                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
                 }
-                Ok(false)
             }
 
-            ActionType::Fill { region, with } => {
+            ExtAction::FillWith {
+                region,
+                with,
+                mode,
+                replace,
+            } => {
+                let with = self.outgoing_block(with)?;
+                // Block tags such as `#minecraft:logs` are left to the server
+                let replace = match replace {
+                    Some(filter) if !filter.starts_with('#') => {
+                        Some(self.outgoing_block(filter)?.into_owned())
+                    }
+                    filter => filter.clone(),
+                };
                 let world_min = self.apply_offset(region[0], offset);
                 let world_max = self.apply_offset(region[1], offset);
+                let suffix = mode.command_suffix(replace.as_deref());
                 let cmd = format!(
-                    "fill {} {} {} {} {} {} {}",
+                    "fill {} {} {} {} {} {} {}{}",
                     world_min[0],
                     world_min[1],
                     world_min[2],
                     world_max[0],
                     world_max[1],
                     world_max[2],
-                    with
+                    with,
+                    suffix
                 );
-                self.bot.send_command(&cmd).await?;
-                println!(
-                    "    {} Tick {}: fill [{},{},{}] to [{},{},{}] = {}",
+                self.send_world_command(tick, &cmd).await?;
+                self.log_action(format!(
+                    "    {} Tick {}: fill [{},{},{}] to [{},{},{}] = {}{}",
                     "→".blue(),
                     tick,
                     region[0][0],
@@ -541,113 +3512,503 @@ impl TestExecutor {
                     region[1][0],
                     region[1][1],
                     region[1][2],
-                    with.dimmed()
-                );
+                    with.dimmed(),
+                    suffix.dimmed()
+                ));
                 Ok(false)
             }
 
-            ActionType::Remove { pos } => {
+            ExtAction::AssertLight { pos, level } => {
                 let world_pos = self.apply_offset(*pos, offset);
-                let cmd = format!(
-                    "setblock {} {} {} air",
-                    world_pos[0], world_pos[1], world_pos[2]
-                );
-                self.bot.send_command(&cmd).await?;
-                println!(
-                    "    {} Tick {}: remove at [{}, {}, {}]",
-                    "→".blue(),
-                    tick,
-                    pos[0],
-                    pos[1],
-                    pos[2]
-                );
-                Ok(false)
+                let actual = self.query_light(world_pos).await?;
+                if actual == *level {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert light at [{}, {}, {}] = {}",
+                        "✓".green(),
+                        tick,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        level.to_string().dimmed()
+                    ));
+                    Ok(true)
+                } else {
+                    anyhow::bail!(
+                        "Light level mismatch at [{}, {}, {}]\n{}",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        format_diff(&level.to_string(), &actual.to_string())
+                    );
+                }
             }
 
-            ActionType::Assert { checks } => {
-                for check in checks {
-                    let world_pos = self.apply_offset(check.pos, offset);
-
-                    // Poll with retries: 10 attempts, 50ms apart = up to 500ms total
-                    // This handles timing issues in CI environments
-                    let actual_block = self
-                        .poll_block_with_retry(world_pos, &check.is, 10, 50)
-                        .await?;
-
-                    let expected_name = check.is.trim_start_matches("minecraft:");
-                    let success = if let Some(ref actual) = actual_block {
-                        let actual_lower = actual.to_lowercase();
-                        let expected_lower = expected_name.to_lowercase().replace("_", "");
-                        actual_lower.contains(&expected_lower)
-                            || actual_lower.replace("_", "").contains(&expected_lower)
-                    } else {
-                        false
-                    };
+            ExtAction::AssertFacing { pos, facing } => {
+                let world_pos = self.apply_offset(*pos, offset);
+                let Some(actual) = self.bot.get_block_property(world_pos, "facing").await? else {
+                    let block_id = self.bot.get_block_id(world_pos).await?;
+                    anyhow::bail!(
+                        "Block at [{}, {}, {}] has no facing ({} has no 'facing' property)",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        block_id
+                    );
+                };
 
-                    if success {
-                        println!(
-                            "    {} Tick {}: assert block at [{}, {}, {}] is {}",
-                            "✓".green(),
-                            tick,
-                            check.pos[0],
-                            check.pos[1],
-                            check.pos[2],
-                            check.is.dimmed()
-                        );
-                    } else {
-                        anyhow::bail!(
-                            "Block at [{}, {}, {}] is not {} (got {:?})",
-                            check.pos[0],
-                            check.pos[1],
-                            check.pos[2],
-                            check.is,
-                            actual_block
-                        );
-                    }
+                if actual == *facing {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert facing at [{}, {}, {}] = {}",
+                        "✓".green(),
+                        tick,
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        facing.dimmed()
+                    ));
+                    Ok(true)
+                } else {
+                    anyhow::bail!(
+                        "Facing mismatch at [{}, {}, {}]\n{}",
+                        pos[0],
+                        pos[1],
+                        pos[2],
+                        format_diff(facing, &actual)
+                    );
                 }
-                Ok(true)
             }
 
-            ActionType::AssertState { pos, state, values } => {
+            ExtAction::AssertFluid { pos, fluid, level } => {
                 let world_pos = self.apply_offset(*pos, offset);
-                let expected_value = &values[value_idx];
-
-                // Poll with retries: 10 attempts, 50ms apart = up to 500ms total
-                // This handles timing issues in CI environments
-                let actual_value = self
-                    .poll_block_state_with_retry(world_pos, state, 10, 50)
-                    .await?;
-
-                let success = if let Some(ref actual) = actual_value {
-                    actual.contains(expected_value)
-                } else {
-                    false
+                let block = self.bot.get_block(world_pos).await?;
+                let (actual_fluid, actual_level) = fluid_of(&block);
+                let fluid = fluid.trim_start_matches("minecraft:");
+                let expected = match level {
+                    Some(level) => format!("{} level {}", fluid, level),
+                    None => fluid.to_string(),
                 };
 
-                if success {
-                    println!(
-                        "    {} Tick {}: assert block at [{}, {}, {}] state {} = {}",
+                if actual_fluid == fluid && level.is_none_or(|level| level == actual_level) {
+                    self.log_action(format!(
+                        "    {} Tick {}: assert fluid at [{}, {}, {}] = {}",
                         "✓".green(),
                         tick,
                         pos[0],
                         pos[1],
                         pos[2],
-                        state.dimmed(),
-                        expected_value.dimmed()
-                    );
+                        expected.dimmed()
+                    ));
                     Ok(true)
                 } else {
                     anyhow::bail!(
-                        "Block at [{}, {}, {}] state {} is not {} (got {:?})",
+                        "Fluid mismatch at [{}, {}, {}]\n{}",
                         pos[0],
                         pos[1],
                         pos[2],
-                        state,
-                        expected_value,
-                        actual_value
+                        format_diff(
+                            &expected,
+                            &format!("{} level {} (block: {})", actual_fluid, actual_level, block)
+                        )
                     );
                 }
             }
+
+            ExtAction::AssertColumn {
+                pos,
+                from_y,
+                to_y,
+                is,
+            } => {
+                let (bottom, top) = (*from_y.min(to_y), *from_y.max(to_y));
+                let blocks = self.bot.snapshot_region(
+                    self.apply_offset([pos[0], bottom, pos[2]], offset),
+                    self.apply_offset([pos[0], top, pos[2]], offset),
+                )?;
+
+                // Walk from `from_y` towards `to_y`, so the first mismatch is the one
+                // nearest the spec's starting end
+                let ys: Vec<i32> = if from_y <= to_y {
+                    (bottom..=top).collect()
+                } else {
+                    (bottom..=top).rev().collect()
+                };
+                let mismatched: Vec<(i32, Option<&BlockInfo>)> = ys
+                    .into_iter()
+                    .map(|y| {
+                        (
+                            y,
+                            blocks.get(&self.apply_offset([pos[0], y, pos[2]], offset)),
+                        )
+                    })
+                    .filter(|(_, block)| !block.is_some_and(|b| self.block_matches(is, b, &[])))
+                    .collect();
+
+                match mismatched.first() {
+                    None => {
+                        self.log_action(format!(
+                            "    {} Tick {}: assert column [{}, {}..{}, {}] is {} ({} blocks)",
+                            "✓".green(),
+                            tick,
+                            pos[0],
+                            from_y,
+                            to_y,
+                            pos[2],
+                            is.dimmed(),
+                            top - bottom + 1
+                        ));
+                        Ok(true)
+                    }
+                    Some((y, actual)) => anyhow::bail!(
+                        "Column [{}, {}..{}, {}] differs first at y={} ({} of {} blocks mismatched)\n{}",
+                        pos[0],
+                        from_y,
+                        to_y,
+                        pos[2],
+                        y,
+                        mismatched.len(),
+                        top - bottom + 1,
+                        format_diff(is, &describe(*actual))
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Delays between the reads of a settling assertion: 25, 50, 100, 200, then the rest
+/// of the budget
+fn settle_delays() -> impl Iterator<Item = u64> {
+    let mut remaining = SETTLE_BUDGET_MS;
+    std::iter::successors(Some(SETTLE_FIRST_DELAY_MS), |delay| {
+        Some((delay * 2).min(SETTLE_MAX_DELAY_MS))
+    })
+    .map_while(move |delay| {
+        let delay = delay.min(remaining);
+        remaining -= delay;
+        (delay > 0).then_some(delay)
+    })
+}
+
+/// Whether a chat line is a player's message, shown by vanilla as `<name> text`
+fn is_player_chat(message: &str) -> bool {
+    message
+        .strip_prefix('<')
+        .and_then(|rest| rest.split_once("> "))
+        .is_some_and(|(name, _)| !name.is_empty() && !name.contains(char::is_whitespace))
+}
+
+/// Whether a chat line is vanilla's error feedback for a world command
+fn is_command_rejection(message: &str) -> bool {
+    COMMAND_REJECTIONS
+        .iter()
+        .any(|fragment| message.contains(fragment))
+}
+
+/// Order the entries due at a tick by phase, then by the partition of their test
+/// The sort is stable, so entries with the same phase and partition keep the order
+/// they were scheduled in, which keeps every test's own entries in spec order.
+fn order_due_entries<T>(entries: &mut [T], key: impl Fn(&T) -> (Phase, usize)) {
+    entries.sort_by_key(key);
+}
+
+/// Whether a test passes: no failed assertions, or at least `min_ratio` of them passed
+fn meets_pass_ratio(passed: usize, failed: usize, min_ratio: Option<f32>) -> bool {
+    match min_ratio {
+        Some(min) if failed > 0 => passed as f32 / (passed + failed) as f32 >= min,
+        _ => failed == 0,
+    }
+}
+
+/// A region with its corners ordered as minimum and maximum on every axis
+fn normalized(region: [[i32; 3]; 2]) -> [[i32; 3]; 2] {
+    [
+        [0, 1, 2].map(|axis| region[0][axis].min(region[1][axis])),
+        [0, 1, 2].map(|axis| region[0][axis].max(region[1][axis])),
+    ]
+}
+
+/// Suffix for a passed assertion that had to wait for its block update
+fn settle_note(waited_ms: u64) -> String {
+    if waited_ms == 0 {
+        String::new()
+    } else {
+        format!(" (settled after {}ms)", waited_ms)
+            .dimmed()
+            .to_string()
+    }
+}
+
+/// Spec-local position of the earliest action of each test, if it has any
+fn first_action_positions(
+    aggregate: &TimelineAggregate,
+    test_count: usize,
+) -> Vec<Option<[i32; 3]>> {
+    let mut ticks: Vec<u32> = aggregate.timeline.keys().copied().collect();
+    ticks.sort_unstable();
+
+    let mut positions = vec![None; test_count];
+    for tick in ticks {
+        for (test_idx, entry, _) in &aggregate.timeline[&tick] {
+            if positions[*test_idx].is_none() {
+                positions[*test_idx] = action_positions(&entry.action_type).first().copied();
+            }
+        }
+    }
+    positions
+}
+
+/// Whether an item id from the world is the spec's item; a bare id means `minecraft:`
+fn item_matches(expected: &str, id: &str) -> bool {
+    if expected.contains(':') {
+        id == expected
+    } else {
+        id.strip_prefix("minecraft:") == Some(expected)
+    }
+}
+
+/// Summary of a container's stacks with counts merged per item, for failure messages
+fn describe_items(items: &[(String, u32)]) -> String {
+    if items.is_empty() {
+        return "empty".to_string();
+    }
+    let mut totals: BTreeMap<&str, u32> = BTreeMap::new();
+    for (id, count) in items {
+        *totals.entry(id).or_default() += count;
+    }
+    totals
+        .into_iter()
+        .map(|(id, count)| format!("{} {}", count, id))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Item id and count of every stack in the SNBT item list printed by `/data get block`
+/// Handles both the current `count: 3` and the pre-1.20.5 `Count: 3b` format; only
+/// top-level fields of each stack are read, so nested item components are ignored.
+fn parse_items(snbt: &str) -> Vec<(String, u32)> {
+    split_top_level(snbt.trim().trim_start_matches('[').trim_end_matches(']'))
+        .into_iter()
+        .filter_map(|stack| {
+            let body = stack.trim().strip_prefix('{')?.strip_suffix('}')?;
+            let mut id = None;
+            let mut count = 1;
+            for field in split_top_level(body) {
+                let Some((key, value)) = field.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                match key.trim().trim_matches('"') {
+                    "id" => id = Some(value.trim_matches('"').to_string()),
+                    "count" | "Count" => {
+                        count = value
+                            .trim_end_matches(['b', 'B', 's', 'S'])
+                            .parse()
+                            .unwrap_or(count)
+                    }
+                    _ => {}
+                }
+            }
+            Some((id?, count))
+        })
+        .collect()
+}
+
+/// Plain text of every line in the SNBT message list of a sign's `front_text`
+/// Lines are JSON text components in quoted strings up to 1.21.4, and plain strings
+/// or SNBT compounds from 1.21.5.
+fn parse_sign_lines(snbt: &str) -> Vec<String> {
+    split_top_level(snbt.trim().trim_start_matches('[').trim_end_matches(']'))
+        .into_iter()
+        .map(|line| {
+            let line = line.trim();
+            if line.starts_with('{') {
+                return serde_json::from_str(line)
+                    .map(|component| component_text(&component))
+                    .unwrap_or_else(|_| snbt_text_field(line));
+            }
+            let text = unquote_snbt(line);
+            match serde_json::from_str::<serde_json::Value>(&text) {
+                Ok(component @ (serde_json::Value::Object(_) | serde_json::Value::String(_))) => {
+                    component_text(&component)
+                }
+                _ => text,
+            }
+        })
+        .collect()
+}
+
+/// Concatenated `text` of a JSON text component and its `extra` children
+fn component_text(component: &serde_json::Value) -> String {
+    match component {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts.iter().map(component_text).collect(),
+        serde_json::Value::Object(fields) => {
+            let mut text = fields
+                .get("text")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if let Some(serde_json::Value::Array(extra)) = fields.get("extra") {
+                text.extend(extra.iter().map(component_text));
+            }
+            text
+        }
+        other => other.to_string(),
+    }
+}
+
+/// The `text` field of an SNBT compound such as `{text:"Hello"}`
+fn snbt_text_field(compound: &str) -> String {
+    let body = compound.trim_start_matches('{').trim_end_matches('}');
+    split_top_level(body)
+        .into_iter()
+        .filter_map(|field| field.split_once(':'))
+        .find(|(key, _)| key.trim().trim_matches('"') == "text")
+        .map(|(_, value)| unquote_snbt(value))
+        .unwrap_or_default()
+}
+
+/// Contents of a single- or double-quoted SNBT string, with escapes resolved
+fn unquote_snbt(value: &str) -> String {
+    let value = value.trim();
+    let quoted = value.len() >= 2
+        && (value.starts_with('"') && value.ends_with('"')
+            || value.starts_with('\'') && value.ends_with('\''));
+    if !quoted {
+        return value.to_string();
+    }
+    let mut text = String::new();
+    let mut escaped = false;
+    for ch in value[1..value.len() - 1].chars() {
+        if escaped {
+            text.push(ch);
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else {
+            text.push(ch);
+        }
+    }
+    text
+}
+
+/// Split SNBT on the commas that are not nested in a compound, list or string
+fn split_top_level(snbt: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut in_string = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (idx, ch) in snbt.char_indices() {
+        if let Some(quote) = in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == quote {
+                in_string = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => in_string = Some(ch),
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&snbt[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if !snbt[start..].trim().is_empty() {
+        parts.push(&snbt[start..]);
+    }
+    parts
+}
+
+/// Block string of a block read from the world, for failure messages
+fn describe(block: Option<&BlockInfo>) -> String {
+    block.map_or_else(|| "<not loaded>".to_string(), ToString::to_string)
+}
+
+/// Two-line expected/actual listing used in assertion failure messages
+fn format_diff(expected: &str, actual: &str) -> String {
+    format!("      expected: {}\n      actual:   {}", expected, actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase_of(entry: serde_json::Value) -> Phase {
+        let entry: TimelineEntry = serde_json::from_value(entry).unwrap();
+        action_phase(&entry.action_type)
+    }
+
+    #[test]
+    fn mutations_run_before_checks_of_other_tests() {
+        let assert_a = phase_of(serde_json::json!({
+            "at": 3, "do": "assert",
+            "checks": [{ "pos": [0, 0, 0], "is": "minecraft:stone" }]
+        }));
+        let place_b = phase_of(serde_json::json!({
+            "at": 3, "do": "place", "pos": [0, 0, 0], "block": "minecraft:stone"
+        }));
+
+        // Test A's assert is scheduled first, whichever partitions the tests are in
+        for partitions in [[0, 0], [0, 1], [1, 0]] {
+            let mut due = vec![(assert_a, 0, "assert a"), (place_b, 1, "place b")];
+            order_due_entries(&mut due, |(phase, test, _)| (*phase, partitions[*test]));
+            let labels: Vec<_> = due.iter().map(|(.., label)| *label).collect();
+            assert_eq!(
+                labels,
+                ["place b", "assert a"],
+                "partitions {:?}",
+                partitions
+            );
         }
     }
+
+    #[test]
+    fn only_vanilla_errors_reject_world_commands() {
+        assert!(is_command_rejection(
+            "Unknown block type 'minecraft:stonee'"
+        ));
+        assert!(is_command_rejection(
+            "Block minecraft:lever does not accept 'maybe' for powered property"
+        ));
+        assert!(is_command_rejection("...setblock 1 2 3 stone[<--[HERE]"));
+        assert!(is_command_rejection("That position is not loaded"));
+        assert!(!is_command_rejection("Changed the block at 1, 2, 3"));
+        assert!(!is_command_rejection(
+            "[Server] Invalid votes are discarded"
+        ));
+        assert!(!is_command_rejection("Expected downtime tonight at 10pm"));
+    }
+
+    #[test]
+    fn player_chat_is_told_apart_from_feedback() {
+        assert!(is_player_chat("<Steve> Invalid wiring here"));
+        assert!(is_player_chat("<Alex> Unknown block type lol"));
+        assert!(!is_player_chat("Unknown block type 'minecraft:stonee'"));
+        assert!(!is_player_chat("<--[HERE]"));
+        assert!(!is_player_chat("Changed the block at 1, 2, 3"));
+    }
+
+    #[test]
+    fn entries_of_a_test_keep_their_order_within_a_phase() {
+        let mut due = vec![
+            (Phase::Check, 0, "a1"),
+            (Phase::Mutate, 0, "a2"),
+            (Phase::Check, 0, "a3"),
+            (Phase::Mutate, 1, "b1"),
+            (Phase::Clone, 0, "a4"),
+            (Phase::Mutate, 0, "a5"),
+            (Phase::Check, 1, "b2"),
+        ];
+        order_due_entries(&mut due, |(phase, partition, _)| (*phase, *partition));
+        let labels: Vec<_> = due.iter().map(|(.., label)| *label).collect();
+        assert_eq!(labels, ["a2", "a5", "b1", "a4", "a1", "a3", "b2"]);
+    }
 }
@@ -0,0 +1,398 @@
+use flint_core::test_spec::TestSpec;
+
+/// Default gap in blocks between neighbouring test areas
+pub const DEFAULT_SPACING: i32 = 5;
+
+/// Horizontal distance within which test areas are served from one bot position
+pub const TELEPORT_RADIUS: i32 = 48;
+
+/// Group test areas so the bot can reach every area of a group with a single teleport
+/// Returns a partition index per area. An area joins the first partition whose anchor
+/// (the center of its first area) is within `radius` blocks on both X and Z.
+pub fn partition_areas(centers: &[[i32; 3]], radius: i32) -> Vec<usize> {
+    let mut anchors: Vec<[i32; 3]> = Vec::new();
+    centers
+        .iter()
+        .map(|center| {
+            let existing = anchors.iter().position(|anchor| {
+                (anchor[0] - center[0]).abs() <= radius && (anchor[2] - center[2]).abs() <= radius
+            });
+            existing.unwrap_or_else(|| {
+                anchors.push(*center);
+                anchors.len() - 1
+            })
+        })
+        .collect()
+}
+
+/// Combine an offset with a spec's `origin` into the offset positions are run at
+/// The origin is subtracted first, so the offset applies to origin-relative positions.
+/// Layout offsets for specs with an origin must therefore come from their
+/// `relative_to` regions, or the area would be moved by the origin twice.
+pub fn apply_origin(offset: [i32; 3], origin: [i32; 3]) -> [i32; 3] {
+    [0, 1, 2].map(|axis| offset[axis] - origin[axis])
+}
+
+/// A region in coordinates relative to `origin`
+pub fn relative_to(region: [[i32; 3]; 2], origin: [i32; 3]) -> [[i32; 3]; 2] {
+    region.map(|corner| [0, 1, 2].map(|axis| corner[axis] - origin[axis]))
+}
+
+/// Compute non-overlapping offsets for a set of tests
+/// Each test is assigned a cell in a square grid on the X/Z plane. Cells are sized
+/// to fit the largest cleanup region plus `spacing`, so no two regions can intersect.
+/// The Y coordinate is left untouched so tests keep their authored heights.
+pub fn compute_offsets(tests: &[TestSpec], spacing: i32) -> Vec<[i32; 3]> {
+    let regions: Vec<[[i32; 3]; 2]> = tests.iter().map(|t| t.cleanup_region()).collect();
+    compute_region_offsets(&regions, spacing)
+}
+
+/// `compute_offsets` for bare regions, e.g. regions grown to make room for a shift
+pub fn compute_region_offsets(regions: &[[[i32; 3]; 2]], spacing: i32) -> Vec<[i32; 3]> {
+    if regions.is_empty() {
+        return Vec::new();
+    }
+
+    // Largest footprint on each horizontal axis determines the cell size
+    let cell_x = regions
+        .iter()
+        .map(|r| (r[1][0] - r[0][0]).abs() + 1)
+        .max()
+        .unwrap_or(1)
+        + spacing.max(0);
+    let cell_z = regions
+        .iter()
+        .map(|r| (r[1][2] - r[0][2]).abs() + 1)
+        .max()
+        .unwrap_or(1)
+        + spacing.max(0);
+
+    let columns = (regions.len() as f64).sqrt().ceil() as usize;
+
+    regions
+        .iter()
+        .enumerate()
+        .map(|(index, region)| {
+            let column = (index % columns) as i32;
+            let row = (index / columns) as i32;
+            let min_x = region[0][0].min(region[1][0]);
+            let min_z = region[0][2].min(region[1][2]);
+            [column * cell_x - min_x, 0, row * cell_z - min_z]
+        })
+        .collect()
+}
+
+/// Bounding box of a region together with its copy moved by `shift`
+/// Laying out the grown region leaves room in the cell for the shifted copy.
+pub fn with_shift(region: [[i32; 3]; 2], shift: [i32; 3]) -> [[i32; 3]; 2] {
+    let min = [0, 1, 2].map(|axis| region[0][axis].min(region[1][axis]));
+    let max = [0, 1, 2].map(|axis| region[0][axis].max(region[1][axis]));
+    [
+        [0, 1, 2].map(|axis| min[axis].min(min[axis] + shift[axis])),
+        [0, 1, 2].map(|axis| max[axis].max(max[axis] + shift[axis])),
+    ]
+}
+
+/// Every pair of areas whose regions share a block, with the shared box
+/// Each area is a list of world-space regions; regions of the same area never count.
+pub fn overlapping_areas(areas: &[Vec<[[i32; 3]; 2]>]) -> Vec<(usize, usize, [[i32; 3]; 2])> {
+    let mut overlaps = Vec::new();
+    for (i, a) in areas.iter().enumerate() {
+        for (j, b) in areas.iter().enumerate().skip(i + 1) {
+            let shared = a
+                .iter()
+                .flat_map(|ra| b.iter().filter_map(move |rb| box_intersection(ra, rb)))
+                .next();
+            if let Some(shared) = shared {
+                overlaps.push((i, j, shared));
+            }
+        }
+    }
+    overlaps
+}
+
+/// Blocks two regions have in common, if any
+fn box_intersection(a: &[[i32; 3]; 2], b: &[[i32; 3]; 2]) -> Option<[[i32; 3]; 2]> {
+    let min = [0, 1, 2].map(|axis| a[0][axis].min(a[1][axis]).max(b[0][axis].min(b[1][axis])));
+    let max = [0, 1, 2].map(|axis| a[0][axis].max(a[1][axis]).min(b[0][axis].max(b[1][axis])));
+    (0..3)
+        .all(|axis| min[axis] <= max[axis])
+        .then_some([min, max])
+}
+
+/// Largest number of blocks a single `/fill` may change
+pub const MAX_FILL_VOLUME: i64 = 32768;
+
+/// Merge regions whose union is exactly a box into single regions
+/// Two boxes merge when they share their extent on two axes and touch or overlap on
+/// the third, and the result stays within `MAX_FILL_VOLUME`. The merged regions cover
+/// exactly the same blocks, so nothing outside the originals is touched.
+pub fn merge_regions(regions: &[[[i32; 3]; 2]]) -> Vec<[[i32; 3]; 2]> {
+    merge_area_regions(&[regions.to_vec()], 0)
+}
+
+/// Merge the regions of several areas like `merge_regions`, but also across gaps of up
+/// to `gap` blocks between different areas
+/// Areas laid out by `compute_region_offsets` with spacing `gap` have nothing in those
+/// gaps, so bridging them only adds blocks no test uses. Gaps between the regions of a
+/// single area are never bridged, since they are left out on purpose.
+pub fn merge_area_regions(areas: &[Vec<[[i32; 3]; 2]>], gap: i32) -> Vec<[[i32; 3]; 2]> {
+    // Every box with the areas it covers
+    let mut merged: Vec<(Vec<usize>, [[i32; 3]; 2])> = areas
+        .iter()
+        .enumerate()
+        .flat_map(|(area, regions)| {
+            regions.iter().map(move |region| {
+                let mut normalized = *region;
+                for axis in 0..3 {
+                    normalized[0][axis] = region[0][axis].min(region[1][axis]);
+                    normalized[1][axis] = region[0][axis].max(region[1][axis]);
+                }
+                (vec![area], normalized)
+            })
+        })
+        .collect();
+
+    'search: loop {
+        for i in 0..merged.len() {
+            for j in i + 1..merged.len() {
+                let shares_area = merged[i].0.iter().any(|area| merged[j].0.contains(area));
+                let allowed_gap = if shares_area { 0 } else { gap.max(0) };
+                if let Some(union) = box_union(&merged[i].1, &merged[j].1, allowed_gap) {
+                    let (covered, _) = merged.swap_remove(j);
+                    merged[i].0.extend(covered);
+                    merged[i].1 = union;
+                    continue 'search;
+                }
+            }
+        }
+        return merged.into_iter().map(|(_, region)| region).collect();
+    }
+}
+
+/// Union of two normalized boxes at most `gap` blocks apart, together with the blocks
+/// between them, if it is itself a box small enough for one fill
+fn box_union(a: &[[i32; 3]; 2], b: &[[i32; 3]; 2], gap: i32) -> Option<[[i32; 3]; 2]> {
+    let differing: Vec<usize> = (0..3)
+        .filter(|&axis| a[0][axis] != b[0][axis] || a[1][axis] != b[1][axis])
+        .collect();
+    let union = [
+        [0, 1, 2].map(|axis| a[0][axis].min(b[0][axis])),
+        [0, 1, 2].map(|axis| a[1][axis].max(b[1][axis])),
+    ];
+    let volume: i64 = (0..3)
+        .map(|axis| i64::from(union[1][axis] - union[0][axis]) + 1)
+        .product();
+
+    match differing.as_slice() {
+        [] => Some(union),
+        [axis] if a[0][*axis] <= b[1][*axis] + 1 + gap && b[0][*axis] <= a[1][*axis] + 1 + gap => {
+            (volume <= MAX_FILL_VOLUME).then_some(union)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(name: &str, region: [[i32; 3]; 2]) -> TestSpec {
+        serde_json::from_value(serde_json::json!({
+            "flintVersion": "0.1",
+            "name": name,
+            "setup": { "cleanup": { "region": region } },
+            "timeline": []
+        }))
+        .unwrap()
+    }
+
+    /// Regions of uneven sizes, some with negative or reversed corners
+    fn uneven_regions(count: usize) -> Vec<[[i32; 3]; 2]> {
+        (0..count as i32)
+            .map(|i| match i % 4 {
+                0 => [[0, 0, 0], [i, 3, 2]],
+                1 => [[-5, -2, -7], [1, 4, 0]],
+                2 => [[3, 10, -1], [-i, 12, -i - 4]],
+                _ => [[-1, 0, -1], [1, 1, 1]],
+            })
+            .collect()
+    }
+
+    fn moved(region: [[i32; 3]; 2], offset: [i32; 3]) -> Vec<[[i32; 3]; 2]> {
+        vec![region.map(|corner| [0, 1, 2].map(|axis| corner[axis] + offset[axis]))]
+    }
+
+    #[test]
+    fn computed_offsets_never_overlap() {
+        for count in [1, 2, 5, 10] {
+            for spacing in [0, 1, DEFAULT_SPACING] {
+                let regions = uneven_regions(count);
+                let specs: Vec<TestSpec> = regions
+                    .iter()
+                    .enumerate()
+                    .map(|(i, region)| spec(&format!("test_{}", i), *region))
+                    .collect();
+                let offsets = compute_offsets(&specs, spacing);
+                assert_eq!(offsets.len(), count);
+
+                let areas: Vec<_> = specs
+                    .iter()
+                    .zip(&offsets)
+                    .map(|(spec, offset)| moved(spec.cleanup_region(), *offset))
+                    .collect();
+                assert_eq!(
+                    overlapping_areas(&areas),
+                    Vec::new(),
+                    "{} tests with spacing {}",
+                    count,
+                    spacing
+                );
+            }
+        }
+    }
+
+    fn blocks(regions: &[[[i32; 3]; 2]]) -> std::collections::HashSet<[i32; 3]> {
+        let mut blocks = std::collections::HashSet::new();
+        for region in regions {
+            let min = [0, 1, 2].map(|axis| region[0][axis].min(region[1][axis]));
+            let max = [0, 1, 2].map(|axis| region[0][axis].max(region[1][axis]));
+            for x in min[0]..=max[0] {
+                for y in min[1]..=max[1] {
+                    for z in min[2]..=max[2] {
+                        blocks.insert([x, y, z]);
+                    }
+                }
+            }
+        }
+        blocks
+    }
+
+    fn volume(region: &[[i32; 3]; 2]) -> i64 {
+        (0..3)
+            .map(|axis| i64::from((region[1][axis] - region[0][axis]).abs()) + 1)
+            .product()
+    }
+
+    #[test]
+    fn merging_thirty_laid_out_areas_needs_fewer_fills() {
+        // 5^3 areas all fit one fill, 16^3 areas need several
+        for size in [4, 15] {
+            let areas = vec![[[0, 0, 0], [size, size, size]]; 30];
+            let offsets = compute_region_offsets(&areas, 0);
+            let cleanup: Vec<[[i32; 3]; 2]> = areas
+                .iter()
+                .zip(&offsets)
+                .flat_map(|(area, offset)| moved(*area, *offset))
+                .collect();
+
+            let merged = merge_regions(&cleanup);
+            assert!(
+                merged.len() < cleanup.len(),
+                "{} fills for {} areas",
+                merged.len(),
+                cleanup.len()
+            );
+            assert!(
+                merged
+                    .iter()
+                    .all(|region| volume(region) <= MAX_FILL_VOLUME)
+            );
+            assert_eq!(blocks(&merged), blocks(&cleanup));
+            let merged_volume: i64 = merged.iter().map(volume).sum();
+            assert_eq!(merged_volume, blocks(&cleanup).len() as i64);
+        }
+    }
+
+    #[test]
+    fn merging_thirty_areas_in_the_default_layout_needs_fewer_fills() {
+        for size in [4, 15] {
+            let areas = vec![[[0, 0, 0], [size, size, size]]; 30];
+            let offsets = compute_region_offsets(&areas, DEFAULT_SPACING);
+            let cleanup: Vec<Vec<[[i32; 3]; 2]>> = areas
+                .iter()
+                .zip(&offsets)
+                .map(|(area, offset)| moved(*area, *offset))
+                .collect();
+            let regions: Vec<[[i32; 3]; 2]> = cleanup.iter().flatten().copied().collect();
+            // Without bridging the gaps, nothing in the default layout touches
+            assert_eq!(merge_regions(&regions).len(), regions.len());
+
+            let merged = merge_area_regions(&cleanup, DEFAULT_SPACING);
+            assert!(
+                merged.len() < regions.len(),
+                "{} fills for {} areas",
+                merged.len(),
+                regions.len()
+            );
+            assert!(
+                merged
+                    .iter()
+                    .all(|region| volume(region) <= MAX_FILL_VOLUME)
+            );
+            // Every area is covered, and nothing beyond the grid is
+            let covered = blocks(&merged);
+            assert!(blocks(&regions).is_subset(&covered));
+            let footprint = [
+                [0, 0, 0],
+                [0, 1, 2].map(|axis| regions.iter().map(|region| region[1][axis]).max().unwrap()),
+            ];
+            assert!(covered.is_subset(&blocks(&[footprint])));
+        }
+    }
+
+    #[test]
+    fn gaps_within_one_area_are_never_bridged() {
+        let split = vec![[[0, 0, 0], [4, 4, 4]], [[8, 0, 0], [12, 4, 4]]];
+        assert_eq!(merge_area_regions(&[split.clone()], DEFAULT_SPACING), split);
+        // The same boxes from two areas are bridged
+        assert_eq!(
+            merge_area_regions(&[vec![split[0]], vec![split[1]]], DEFAULT_SPACING),
+            vec![[[0, 0, 0], [12, 4, 4]]]
+        );
+    }
+
+    #[test]
+    fn origin_is_applied_once() {
+        let origin = [1200, 64, -300];
+        let local = [[0, 0, 0], [3, 2, 3]];
+        let absolute = moved(local, origin)[0];
+
+        // Run with a hand-picked offset, e.g. by a library caller
+        assert_eq!(
+            moved(absolute, apply_origin([0, 0, 0], origin)),
+            vec![local]
+        );
+
+        // Laid out on the grid next to a spec written in local coordinates
+        let offsets =
+            compute_region_offsets(&[local, relative_to(absolute, origin)], DEFAULT_SPACING);
+        let expected = compute_region_offsets(&[local, local], DEFAULT_SPACING);
+        assert_eq!(
+            moved(absolute, apply_origin(offsets[1], origin)),
+            moved(local, expected[1])
+        );
+    }
+
+    #[test]
+    fn computed_region_offsets_never_overlap() {
+        for count in [2, 5, 10] {
+            for spacing in [0, 3] {
+                let regions = uneven_regions(count);
+                let offsets = compute_region_offsets(&regions, spacing);
+                let areas: Vec<_> = regions
+                    .iter()
+                    .zip(&offsets)
+                    .map(|(region, offset)| moved(*region, *offset))
+                    .collect();
+                assert_eq!(
+                    overlapping_areas(&areas),
+                    Vec::new(),
+                    "{} regions with spacing {}",
+                    count,
+                    spacing
+                );
+            }
+        }
+    }
+}
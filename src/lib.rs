@@ -0,0 +1,47 @@
+//! Run Flint tests against a live Minecraft server
+//!
+//! The binary is a thin wrapper around this library, so the same executor can be
+//! driven from Rust integration tests with programmatically-constructed specs.
+//!
+//! ```no_run
+//! use flintcli::{SpecExtras, TestExecutor, TestSpec};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let spec: TestSpec = serde_json::from_value(serde_json::json!({
+//!     "flintVersion": "0.1",
+//!     "name": "stone_is_placed",
+//!     "setup": { "cleanup": { "region": [[0, 99, 0], [2, 101, 2]] } },
+//!     "timeline": [
+//!         { "at": 0, "do": "place", "pos": [1, 100, 1], "block": "minecraft:stone" },
+//!         { "at": 1, "do": "assert", "checks": [{ "pos": [1, 100, 1], "is": "minecraft:stone" }] }
+//!     ]
+//! }))?;
+//!
+//! let mut executor = TestExecutor::new();
+//! executor.connect("localhost:25565").await?;
+//! let results = executor
+//!     .run_tests_parallel(&[(spec, [0, 0, 0])], &[SpecExtras::default()], false)
+//!     .await?;
+//! assert!(results.iter().all(|r| r.success));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod blocks;
+mod bot;
+pub mod cache;
+pub mod config;
+pub mod executor;
+pub mod layout;
+pub mod report;
+pub mod scaffold;
+pub mod spec;
+pub mod suite;
+pub mod validate;
+
+pub use bot::ServerInfo;
+pub use executor::{AssertionResult, RunStats, TestExecutor};
+pub use flint_core::results::TestResult;
+pub use flint_core::test_spec::TestSpec;
+pub use spec::{SpecExtras, load_spec};
+pub use suite::Suite;
@@ -1,26 +1,80 @@
-mod bot;
-mod executor;
-
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use flint_core::loader::TestLoader;
-use flint_core::spatial::calculate_test_offset_default;
-use flint_core::test_spec::TestSpec;
-use std::path::PathBuf;
+use flintcli::{
+    TestSpec, cache, config, executor, layout, report, scaffold, spec, suite, validate,
+};
+use notify::{RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing_subscriber::EnvFilter;
 
+/// Print decorative output, moved to stderr when a machine-readable report owns stdout
+macro_rules! status {
+    ($machine:expr) => {
+        if $machine {
+            eprintln!()
+        } else {
+            println!()
+        }
+    };
+    ($machine:expr, $($arg:tt)*) => {
+        if $machine {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Exit code when the run could not start, e.g. the server is unreachable
+const EXIT_INFRA_FAILURE: i32 = 2;
+
+/// Quiet period after the last file event before --watch re-runs, so a burst of
+/// saves (or an editor writing one file several times) triggers a single run
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
 #[derive(Parser, Debug)]
 #[command(name = "flintmc")]
 #[command(about = "Minecraft server testing framework", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to test file or directory
     #[arg(value_name = "PATH")]
     path: Option<PathBuf>,
 
-    /// Server address (e.g., localhost:25565)
+    /// Server address (e.g., localhost:25565), overrides the config file; repeat to run
+    /// the suite against several servers and compare the results
     #[arg(short, long)]
-    server: String,
+    server: Vec<String>,
+
+    /// Server software: vanilla, paper, fabric, or legacy for servers without /tick
+    #[arg(long, value_enum)]
+    server_flavor: Option<executor::ServerFlavor>,
+
+    /// Offline-mode username for the bot, overrides the config file [default: FlintMC_TestBot]
+    #[arg(long)]
+    username: Option<String>,
+
+    /// Seconds to wait for the bot's connection to initialize [default: 5]
+    #[arg(long, value_name = "SECS")]
+    init_timeout: Option<u64>,
+
+    /// Seconds to wait for the connected bot to enter the game [default: 10]
+    #[arg(long, value_name = "SECS")]
+    join_timeout: Option<u64>,
+
+    /// Append a short random suffix to the username so parallel runs don't collide
+    #[arg(long)]
+    random_suffix: bool,
+
+    /// Config file to load instead of ./flint.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
 
     /// Recursively search directories for test files
     #[arg(short, long)]
@@ -34,31 +88,243 @@ struct Args {
     #[arg(long)]
     chat_control: bool,
 
-    /// Filter tests by tags (can be specified multiple times)
+    /// Keep a copy of the test areas at tick 0 and offer to replay the tests from it
+    #[arg(long, conflicts_with_all = ["watch", "batch"])]
+    debug: bool,
+
+    /// Only run specs with this tag; repeat to run specs with any of several tags
+    /// Without PATH, specs are searched for by tag instead
     #[arg(short = 't', long = "tag")]
     tags: Vec<String>,
+
+    /// Skip specs with this tag (can be specified multiple times)
+    #[arg(long = "exclude-tag", value_name = "TAG")]
+    exclude_tags: Vec<String>,
+
+    /// Gap in blocks between automatically arranged test areas [default: 5]
+    #[arg(long)]
+    spacing: Option<i32>,
+
+    /// Hide per-action output and show a progress bar instead (errors are still printed)
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Leave the areas of failed tests intact for in-game inspection
+    #[arg(long)]
+    keep_on_failure: bool,
+
+    /// Skip the rest of a test once one of its placements, fills, clones or commands fails
+    #[arg(long)]
+    abort_on_setup_error: bool,
+
+    /// Print the current tick and the tests remaining every 5 seconds while tests run
+    #[arg(long)]
+    heartbeat: bool,
+
+    /// Fail instead of warning when a spec touches positions outside its cleanup region
+    #[arg(long)]
+    strict: bool,
+
+    /// Print results as TAP version 13 on stdout (other output moves to stderr)
+    #[arg(long)]
+    tap: bool,
+
+    /// Before a test's first failure, list the entries it ran just before
+    #[arg(long)]
+    verbose_failures: bool,
+
+    /// Also print each failure as `FAIL file:<path> line:<n> test:<name> tick:<t>`
+    #[arg(long)]
+    editor: bool,
+
+    /// Log verbosity for diagnostics on stderr (overrides RUST_LOG)
+    #[arg(long, value_enum)]
+    log_level: Option<LogLevel>,
+
+    /// Skip the commands run after connecting (default: /gamemode creative @s)
+    #[arg(long)]
+    no_connect_commands: bool,
+
+    /// Do not teleport the bot next to the test areas before running them
+    #[arg(long)]
+    no_teleport: bool,
+
+    /// Only run spec files that git reports as changed since this ref (e.g. HEAD, main)
+    #[arg(long, value_name = "GIT_REF")]
+    since: Option<String>,
+
+    /// Only run the tests that failed in the last run, as recorded in .flint-cache.json
+    #[arg(long)]
+    rerun_failed: bool,
+
+    /// Reset the server's random sequences to this seed before every run
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Run the spec files in a random order, which also moves them to other grid cells;
+    /// give the printed seed to repeat an order. Put PATH before it when omitting SEED
+    #[arg(long, value_name = "SEED", num_args = 0..=1)]
+    shuffle: Option<Option<u64>>,
+
+    /// Run the whole suite N times and report tests that do not pass consistently
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    repeat: u32,
+
+    /// Send at most N world-editing entries per tick before pausing for the server to catch up
+    #[arg(long, value_name = "N")]
+    max_burst: Option<usize>,
+
+    /// Stop the whole run after SECS seconds, failing every test that did not finish
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Load and run specs N at a time, cleaning up each batch before the next
+    #[arg(long, value_name = "N")]
+    batch: Option<usize>,
+
+    /// After the first run, stay connected and re-run specs under PATH when they change
+    #[arg(long)]
+    watch: bool,
+
+    /// Write every individual assertion's outcome, grouped by test, to FILE as JSON
+    #[arg(long, value_name = "FILE")]
+    assertions: Option<PathBuf>,
+
+    /// Append every command sent to FILE, grouped by tick and test, to replay a run by hand
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+
+    /// Run the first test once before the suite and discard its result, to prime chunk
+    /// loading and the command pipeline
+    #[arg(long)]
+    warmup: bool,
+
+    /// Halt the timeline at the end of tick N, unfreeze time and leave the test areas intact
+    #[arg(long, value_name = "N", conflicts_with_all = ["watch", "batch", "debug"])]
+    stop_at_tick: Option<u32>,
+
+    /// Suite file with setup/teardown entries shared by all tests
+    #[arg(long, value_name = "FILE")]
+    suite: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check spec files for problems without connecting to a server
+    Validate {
+        /// Path to test file or directory
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Recursively search directories for test files
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Print the tests under a path with their length and cleanup size, without connecting
+    List {
+        /// Path to test file or directory
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Recursively search directories for test files
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Compare two TAP reports and fail only on tests that newly fail
+    Compare {
+        /// Report of the baseline run
+        #[arg(value_name = "BASELINE")]
+        baseline: PathBuf,
+
+        /// Report of the current run, or `-` for stdin
+        #[arg(value_name = "CURRENT")]
+        current: PathBuf,
+    },
+    /// Write an example spec and a starter flint.toml to get going
+    Init {
+        /// Spec file to create, or a directory to create example_test.json in
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn as_filter(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Setup logging
+    let args = Args::parse();
+
+    // Setup logging: --log-level > RUST_LOG > info
+    // Logs go to stderr so they never mix with report output on stdout
+    let filter = match args.log_level {
+        Some(level) => EnvFilter::new(level.as_filter()),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
     tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
-        )
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
         .init();
 
-    let args = Args::parse();
+    match &args.command {
+        Some(Command::Validate { path, recursive }) => return validate_specs(path, *recursive),
+        Some(Command::Init { path, force }) => return init(path, *force),
+        Some(Command::List { path, recursive }) => return list_specs(path, *recursive),
+        Some(Command::Compare { baseline, current }) => return compare_reports(baseline, current),
+        None => {}
+    }
 
-    println!("{}", "FlintMC - Minecraft Testing Framework".green().bold());
-    println!();
+    let config = config::Config::load(args.config.as_deref())?;
+    let machine_output = args.tap;
 
-    // Collect test files - use tags if provided, otherwise use path
-    let test_files = if !args.tags.is_empty() {
-        println!("{} Filtering by tags: {:?}", "→".blue(), args.tags);
-        TestLoader::collect_by_tags(&args.tags)?
-    } else if let Some(ref path) = args.path {
+    // Resolve settings: flag > config file > built-in default
+    let servers: Vec<String> = if args.server.is_empty() {
+        config.server.clone().into_iter().collect()
+    } else {
+        args.server.clone()
+    };
+    let spacing = layout_spacing(&args, &config);
+
+    status!(
+        machine_output,
+        "{}",
+        "FlintMC - Minecraft Testing Framework".green().bold()
+    );
+    status!(machine_output);
+
+    // Collect test files from the path, or search by tags without one
+    let test_files = if let Some(ref path) = args.path {
         TestLoader::collect_test_files(path, args.recursive)?
+    } else if !args.tags.is_empty() {
+        status!(
+            machine_output,
+            "{} Filtering by tags: {:?}",
+            "→".blue(),
+            args.tags
+        );
+        TestLoader::collect_by_tags(&args.tags)?
     } else {
         eprintln!(
             "{} Must specify either a path or tags to filter by",
@@ -67,98 +333,1856 @@ async fn main() -> Result<()> {
         std::process::exit(1);
     };
 
+    if args.watch && args.path.is_none() {
+        eprintln!("{} --watch needs a PATH to watch", "Error:".red().bold());
+        std::process::exit(1);
+    }
+
+    // --tag keeps specs with any of the tags, --exclude-tag then drops specs with any of
+    // its tags; a spec whose tags can't be read is kept so loading reports the problem
+    let test_files =
+        if (args.path.is_some() && !args.tags.is_empty()) || !args.exclude_tags.is_empty() {
+            let total = test_files.len();
+            let test_files: Vec<_> = test_files
+                .into_iter()
+                .filter(|file| {
+                    let Ok(tags) = spec::load_tags(file) else {
+                        return true;
+                    };
+                    let included = args.path.is_none()
+                        || args.tags.is_empty()
+                        || tags.iter().any(|tag| args.tags.contains(tag));
+                    included && !tags.iter().any(|tag| args.exclude_tags.contains(tag))
+                })
+                .collect();
+            status!(
+                machine_output,
+                "{} {} of {} test file(s) match the tag filters",
+                "→".blue(),
+                test_files.len(),
+                total
+            );
+            test_files
+        } else {
+            test_files
+        };
+
     if test_files.is_empty() {
-        let location = if !args.tags.is_empty() {
+        let location = if args.path.is_none() {
             format!("with tags: {:?}", args.tags)
         } else {
             format!("at: {}", args.path.as_ref().unwrap().display())
         };
         eprintln!("{} No test files found {}", "Error:".red().bold(), location);
+        if args.tap {
+            report::write_tap(&mut std::io::stdout(), &[], &HashMap::new())?;
+        }
         std::process::exit(1);
     }
 
-    println!("Found {} test file(s)\n", test_files.len());
+    // Narrow down to files changed since the given ref; any git problem runs everything
+    let test_files = match args.since {
+        Some(ref since) => match changed_files(since) {
+            Ok(changed) => {
+                let total = test_files.len();
+                let test_files: Vec<_> = test_files
+                    .into_iter()
+                    .filter(|file| {
+                        file.canonicalize()
+                            .is_ok_and(|path| changed.contains(&path))
+                    })
+                    .collect();
+                status!(
+                    machine_output,
+                    "{} {} of {} test file(s) changed since {}",
+                    "→".blue(),
+                    test_files.len(),
+                    total,
+                    since
+                );
+                if test_files.is_empty() {
+                    if args.tap {
+                        report::write_tap(&mut std::io::stdout(), &[], &HashMap::new())?;
+                    }
+                    return Ok(());
+                }
+                test_files
+            }
+            Err(e) => {
+                eprintln!(
+                    "{} Could not list changes since {} ({}), running all tests",
+                    "Warning:".yellow().bold(),
+                    since,
+                    e
+                );
+                test_files
+            }
+        },
+        None => test_files,
+    };
+
+    // Narrow down to the tests that failed last time; without a cache everything runs
+    let test_files = if args.rerun_failed {
+        match cache::ResultsCache::load(Path::new(cache::CACHE_FILE)) {
+            Ok(cache) => {
+                let failed: HashSet<PathBuf> =
+                    cache.failed.into_iter().map(|test| test.file).collect();
+                let total = test_files.len();
+                let test_files: Vec<_> = test_files
+                    .into_iter()
+                    .filter(|file| file.canonicalize().is_ok_and(|path| failed.contains(&path)))
+                    .collect();
+                status!(
+                    machine_output,
+                    "{} Re-running {} of {} test file(s) that failed last time",
+                    "→".blue(),
+                    test_files.len(),
+                    total
+                );
+                if test_files.is_empty() {
+                    if args.tap {
+                        report::write_tap(&mut std::io::stdout(), &[], &HashMap::new())?;
+                    }
+                    return Ok(());
+                }
+                test_files
+            }
+            Err(e) => {
+                eprintln!(
+                    "{} No usable results cache ({:#}), running all tests",
+                    "Warning:".yellow().bold(),
+                    e
+                );
+                test_files
+            }
+        }
+    } else {
+        test_files
+    };
+
+    status!(machine_output, "Found {} test file(s)\n", test_files.len());
+
+    // Shuffled before batching, so batches, grid cells and the order of the tests'
+    // entries within a tick all change with the seed
+    let shuffle_seed = args.shuffle.map(|seed| {
+        seed.unwrap_or_else(|| {
+            use std::hash::BuildHasher;
+            std::collections::hash_map::RandomState::new().hash_one(std::process::id())
+        })
+    });
+    let test_files = match shuffle_seed {
+        Some(seed) => {
+            let mut test_files = test_files;
+            shuffle(&mut test_files, seed);
+            status!(
+                machine_output,
+                "{} Shuffled test order with seed {} (repeat with --shuffle {})\n",
+                "→".blue(),
+                seed,
+                seed
+            );
+            test_files
+        }
+        None => test_files,
+    };
+
+    // Specs may pin a server; a spec whose server can't be read is left to the
+    // default so loading reports the problem
+    let pinned: Vec<Option<String>> = test_files
+        .iter()
+        .map(|file| spec::load_server(file).ok().flatten())
+        .collect();
+    if servers.len() > 1 {
+        if let Some(file) = test_files
+            .iter()
+            .zip(&pinned)
+            .find_map(|(file, server)| server.as_ref().map(|_| file))
+        {
+            eprintln!(
+                "{} {} sets `server`, so it can't be compared across several --server",
+                "Error:".red().bold(),
+                file.display()
+            );
+            std::process::exit(1);
+        }
+        return compare_servers(
+            &args,
+            &config,
+            &servers,
+            &test_files,
+            spacing,
+            machine_output,
+        )
+        .await;
+    }
+
+    // Group the tests by server in order of first use; unpinned tests go to the default
+    let mut groups: Vec<(String, Vec<PathBuf>)> = Vec::new();
+    for (file, server) in test_files.iter().zip(pinned) {
+        let Some(server) = server.or_else(|| servers.first().cloned()) else {
+            eprintln!(
+                "{} No server address for {}: pass --server, set `server` in {} or in the spec",
+                "Error:".red().bold(),
+                file.display(),
+                config::DEFAULT_CONFIG_FILE
+            );
+            std::process::exit(1);
+        };
+        match groups.iter_mut().find(|(group, _)| *group == server) {
+            Some((_, files)) => files.push(file.clone()),
+            None => groups.push((server, vec![file.clone()])),
+        }
+    }
+    if groups.len() > 1 {
+        return run_per_server(&args, &config, &groups, spacing, machine_output).await;
+    }
+    let server = groups.remove(0).0;
 
     // Connect to server
-    let mut executor = executor::TestExecutor::new();
+    let mut executor = configure_executor(&args, &config, machine_output)?;
 
-    // Enable chat control if requested
-    if args.chat_control {
-        executor.set_chat_control(true);
-        println!(
-            "{} Chat control enabled - you can type 's' or 'c' in game chat",
-            "→".yellow()
+    status!(
+        machine_output,
+        "{} Connecting to {} as {}...",
+        "→".blue(),
+        server,
+        executor.username()
+    );
+    if let Err(e) = executor.connect(&server).await {
+        eprintln!(
+            "{} Could not connect to {}: {:#}",
+            "Error:".red().bold(),
+            server,
+            e
+        );
+        // Same report shape as a test run, with one synthetic failed test and a
+        // distinct exit code, so CI can tell infrastructure problems from regressions
+        let connection = flintcli::TestResult::new("connection".to_string())
+            .with_failure_reason(format!("Could not connect to {}\n{:#}", server, e));
+        if args.tap {
+            report::write_tap(&mut std::io::stdout(), &[connection], &HashMap::new())?;
+        }
+        status!(
+            machine_output,
+            "SUMMARY tests=1 passed=0 failed=1 ticks=0 duration=0.0s"
         );
+        std::process::exit(EXIT_INFRA_FAILURE);
     }
+    status!(machine_output, "{} Connected successfully", "✓".green());
+    let bot_protocol = flintcli::ServerInfo::bot_protocol();
+    let server_version = executor.server_info().map(|info| {
+        format!(
+            "{} (protocol {}, bot protocol {})",
+            info.version, info.protocol, bot_protocol
+        )
+    });
+    match &server_version {
+        Some(version) => status!(machine_output, "  Server: {}", version),
+        None => status!(
+            machine_output,
+            "  Server: version unknown (no status response)"
+        ),
+    }
+    if executor
+        .server_info()
+        .is_some_and(|info| info.protocol != bot_protocol)
+    {
+        status!(
+            machine_output,
+            "  {} Server and bot protocols differ (the server likely translates with ViaVersion); block states may not match what the spec expects",
+            "Warning:".yellow().bold()
+        );
+    }
+    status!(machine_output);
 
-    println!("{} Connecting to {}...", "→".blue(), args.server);
-    executor.connect(&args.server).await?;
-    println!("{} Connected successfully\n", "✓".green());
-
-    // Load all tests and calculate offsets
+    // Specs are loaded one batch at a time, so only the current batch is held in
+    // memory and built in the world; without --batch everything is one batch
     let total_tests = test_files.len();
-    let mut tests_with_offsets = Vec::new();
+    let batch_size = args.batch.unwrap_or(total_tests).max(1);
+    let batch_count = total_tests.div_ceil(batch_size);
 
-    for (test_index, test_file) in test_files.iter().enumerate() {
-        match TestSpec::from_file(test_file) {
-            Ok(test) => {
-                let offset = calculate_test_offset_default(test_index, total_tests);
-                println!(
-                    "  {} Grid position: {} (offset: [{}, {}, {}])",
-                    "→".blue(),
-                    format!("[{}/{}]", test_index + 1, total_tests).dimmed(),
-                    offset[0],
-                    offset[1],
-                    offset[2]
+    // Run each batch in parallel using merged timeline, repeated to detect flaky tests
+    // Each run cleans every area before and after, so repeats start from a clean world
+    let repeat = args.repeat.max(1);
+    let mut results: Vec<flintcli::TestResult> = Vec::new();
+    let mut pass_counts: Vec<u32> = Vec::new();
+    let mut stats = executor::RunStats::default();
+    let deadline = args
+        .timeout
+        .map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs));
+    let mut timed_out = false;
+    // Spec file of every result, in order
+    let mut result_files: Vec<PathBuf> = Vec::new();
+    // Description and metadata of every test, by name, for the TAP report
+    let mut details: HashMap<String, report::TestDetails> = HashMap::new();
+    // Assertions of every test, by name, from the run its reported result comes from
+    let mut assertion_log: HashMap<String, Vec<flintcli::AssertionResult>> = HashMap::new();
+    'batches: for (batch_index, batch_files) in test_files.chunks(batch_size).enumerate() {
+        if batch_count > 1 {
+            status!(
+                machine_output,
+                "{} Batch {}/{} ({} tests)",
+                "→".blue().bold(),
+                batch_index + 1,
+                batch_count,
+                batch_files.len()
+            );
+        }
+        let Batch {
+            tests: tests_with_offsets,
+            extras,
+            files: batch_test_files,
+        } = match load_batch(
+            batch_files,
+            batch_index * batch_size,
+            total_tests,
+            spacing,
+            machine_output,
+        ) {
+            Ok(batch) => batch,
+            Err(e) => {
+                eprintln!("{} {:#}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        };
+        result_files.extend(batch_test_files);
+        for ((test, _), extras) in tests_with_offsets.iter().zip(&extras) {
+            details.insert(test.name.clone(), report::TestDetails::from_extras(extras));
+        }
+        status!(machine_output);
+
+        if args.warmup && batch_index == 0 {
+            warmup(&mut executor, &tests_with_offsets, &extras, machine_output).await?;
+        }
+
+        let mut batch_results: Vec<flintcli::TestResult> = Vec::new();
+        let mut batch_pass_counts = vec![0u32; tests_with_offsets.len()];
+        for run in 1..=repeat {
+            if repeat > 1 {
+                status!(
+                    machine_output,
+                    "{} Run {}/{}",
+                    "→".blue().bold(),
+                    run,
+                    repeat
                 );
-                tests_with_offsets.push((test, offset));
             }
-            Err(e) => {
-                eprintln!(
-                    "{} Failed to load test {}: {}",
-                    "Error:".red().bold(),
-                    test_file.display(),
-                    e
+            let run_results = tokio::select! {
+                results = executor.run_tests_parallel(
+                    &tests_with_offsets,
+                    &extras,
+                    args.break_after_setup && batch_index == 0 && run == 1,
+                ) => Some(results?),
+                _ = tokio::signal::ctrl_c() => None,
+                _ = deadline_reached(deadline) => {
+                    timed_out = true;
+                    None
+                }
+            };
+            let Some(run_results) = run_results else {
+                if !timed_out {
+                    restore_after_interrupt(&mut executor, "Interrupted").await;
+                    std::process::exit(130);
+                }
+
+                let progress = match executor.timeline_position() {
+                    Some((tick, max_tick)) => format!("timeline at tick {} of {}", tick, max_tick),
+                    None => "timeline not started".to_string(),
+                };
+                let reason = format!(
+                    "suite timeout after {}s ({}, batch {}/{}, run {}/{})",
+                    args.timeout.unwrap_or_default(),
+                    progress,
+                    batch_index + 1,
+                    batch_count,
+                    run,
+                    repeat
                 );
-                std::process::exit(1);
+                eprintln!("\n{} {}", "Error:".red().bold(), reason);
+                restore_after_interrupt(&mut executor, "Suite timeout").await;
+
+                // Tests that finished an earlier run keep that result; the rest fail
+                if batch_results.is_empty() {
+                    batch_results = tests_with_offsets
+                        .iter()
+                        .map(|(test, _)| {
+                            flintcli::TestResult::new(test.name.clone())
+                                .with_failure_reason(reason.clone())
+                        })
+                        .collect();
+                }
+                results.extend(batch_results);
+                pass_counts.extend(batch_pass_counts);
+                for file in test_files.iter().skip((batch_index + 1) * batch_size) {
+                    let name = match spec::load_spec(file) {
+                        Ok((test, extras)) => {
+                            details.insert(
+                                test.name.clone(),
+                                report::TestDetails::from_extras(&extras),
+                            );
+                            test.name
+                        }
+                        Err(_) => file.display().to_string(),
+                    };
+                    results
+                        .push(flintcli::TestResult::new(name).with_failure_reason(reason.clone()));
+                    pass_counts.push(0);
+                    result_files.push(file.clone());
+                }
+                break 'batches;
+            };
+            stats.ticks += executor.stats().ticks;
+            stats.duration += executor.stats().duration;
+            // Report the largest drift of any run
+            if let Some(drift) = executor.stats().drift
+                && stats.drift.is_none_or(|worst| drift.abs() > worst.abs())
+            {
+                stats.drift = Some(drift);
+            }
+
+            for (count, result) in batch_pass_counts.iter_mut().zip(&run_results) {
+                if result.success {
+                    *count += 1;
+                }
+            }
+            // Keep the first failure of every test, otherwise its latest pass
+            if batch_results.is_empty() {
+                for result in &run_results {
+                    assertion_log.insert(
+                        result.test_name.clone(),
+                        assertions_of(&executor, &result.test_name),
+                    );
+                }
+                batch_results = run_results;
+            } else {
+                for (kept, result) in batch_results.iter_mut().zip(run_results) {
+                    if kept.success {
+                        assertion_log.insert(
+                            result.test_name.clone(),
+                            assertions_of(&executor, &result.test_name),
+                        );
+                        *kept = result;
+                    }
+                }
+            }
+
+            // Another run would clean the areas left for inspection
+            if executor.stats().stopped_at.is_some() {
+                break;
             }
         }
-    }
 
-    println!();
+        // Replays are for looking around in-game; the report keeps the first results
+        if args.debug {
+            debug_replays(&mut executor, &tests_with_offsets, &extras, machine_output).await?;
+        }
 
-    // Run all tests in parallel using merged timeline
-    let results = executor
-        .run_tests_parallel(&tests_with_offsets, args.break_after_setup)
-        .await?;
+        if batch_count > 1 {
+            let passed = batch_results.iter().filter(|r| r.success).count();
+            status!(
+                machine_output,
+                "\n{} Batch {}/{}: {} passed, {} failed\n",
+                "→".blue().bold(),
+                batch_index + 1,
+                batch_count,
+                passed.to_string().green(),
+                (batch_results.len() - passed).to_string().red()
+            );
+        }
+        results.extend(batch_results);
+        pass_counts.extend(batch_pass_counts);
+    }
+    let flaky: Vec<(&str, u32)> = results
+        .iter()
+        .zip(&pass_counts)
+        .filter(|(_, passes)| **passes > 0 && **passes < repeat)
+        .map(|(result, passes)| (result.test_name.as_str(), *passes))
+        .collect();
 
     // Print summary
-    println!("\n{}", "═".repeat(60).dimmed());
-    println!("{}", "Test Summary".cyan().bold());
-    println!("{}", "═".repeat(60).dimmed());
+    status!(machine_output, "\n{}", "═".repeat(60).dimmed());
+    status!(machine_output, "{}", "Test Summary".cyan().bold());
+    status!(machine_output, "{}", "═".repeat(60).dimmed());
 
     let total_passed = results.iter().filter(|r| r.success).count();
     let total_failed = results.len() - total_passed;
+    let is_expected_failure = |result: &flintcli::TestResult| {
+        details
+            .get(&result.test_name)
+            .is_some_and(|details| details.is_expected_failure(result))
+    };
+    let expected_failures = results
+        .iter()
+        .filter(|&result| is_expected_failure(result))
+        .count();
 
     for result in &results {
-        let status = if result.success {
+        let status = if is_expected_failure(result) {
+            "XFAIL".green().bold()
+        } else if result.success {
             "PASS".green().bold()
         } else {
             "FAIL".red().bold()
         };
-        println!("  [{}] {}", status, result.test_name);
+        status!(machine_output, "  [{}] {}", status, result.test_name);
     }
 
-    println!(
-        "\n{} tests run: {} passed, {} failed\n",
+    if repeat > 1 {
+        status!(machine_output);
+        if flaky.is_empty() {
+            status!(
+                machine_output,
+                "{} Every test gave the same result in all {} runs",
+                "✓".green(),
+                repeat
+            );
+        }
+        for (name, passes) in &flaky {
+            status!(
+                machine_output,
+                "  [{}] {} passed {}/{} runs",
+                "FLAKY".yellow().bold(),
+                name,
+                passes,
+                repeat
+            );
+        }
+    }
+
+    let xfail_count = if expected_failures > 0 {
+        format!(" ({} expected failures)", expected_failures)
+    } else {
+        String::new()
+    };
+    status!(
+        machine_output,
+        "\n{} tests run: {} passed{}, {} failed\n",
         results.len(),
         total_passed.to_string().green(),
+        xfail_count,
         total_failed.to_string().red()
     );
 
+    if args.tap {
+        report::write_tap(&mut std::io::stdout(), &results, &details)?;
+        if let Some(ref server_version) = server_version {
+            println!("# server: {}", server_version);
+        }
+    }
+    if let Some(ref path) = args.assertions {
+        write_assertions(path, &results, &assertion_log)?;
+    }
+
+    let results_cache = cache::ResultsCache {
+        failed: results
+            .iter()
+            .zip(&result_files)
+            .filter(|(result, _)| !result.success)
+            .map(|(result, file)| cache::FailedTest {
+                name: result.test_name.clone(),
+                file: file.canonicalize().unwrap_or_else(|_| file.clone()),
+            })
+            .collect(),
+    };
+    if let Err(e) = results_cache.save(Path::new(cache::CACHE_FILE)) {
+        eprintln!("{} {:#}", "Warning:".yellow().bold(), e);
+    }
+
+    if let Some(drift) = stats.drift
+        && drift != 0
+    {
+        status!(
+            machine_output,
+            "{} Server game time drifted {:+} ticks from the ticks advanced; time was not fully frozen, so timing results are unreliable\n",
+            "Warning:".yellow().bold(),
+            drift
+        );
+    }
+
+    // Single greppable line, always printed last so scripts can `tail -1`
+    let drift_note = stats
+        .drift
+        .map(|drift| format!(" drift={}", drift))
+        .unwrap_or_default();
+    let seed_note = args
+        .seed
+        .map(|seed| format!(" seed={}", seed))
+        .unwrap_or_default();
+    let xfail_note = if expected_failures > 0 {
+        format!(" xfail={}", expected_failures)
+    } else {
+        String::new()
+    };
+    let shuffle_note = shuffle_seed
+        .map(|seed| format!(" shuffle={}", seed))
+        .unwrap_or_default();
+    let repeat_note = if repeat > 1 {
+        format!(" runs={} flaky={}", repeat, flaky.len())
+    } else {
+        String::new()
+    };
+    status!(
+        machine_output,
+        "SUMMARY tests={} passed={} failed={}{}{} ticks={}{} duration={:.1}s{}{}",
+        results.len(),
+        total_passed,
+        total_failed,
+        xfail_note,
+        repeat_note,
+        stats.ticks,
+        drift_note,
+        stats.duration.as_secs_f64(),
+        seed_note,
+        shuffle_note
+    );
+
+    if let Some(ref path) = args.path
+        && args.watch
+        && !timed_out
+    {
+        return watch_specs(&mut executor, path, args.recursive, spacing, machine_output).await;
+    }
+
     if total_failed > 0 {
         std::process::exit(1);
     }
 
     Ok(())
 }
+
+/// Run the suite against every server in turn and compare the results per test
+/// All bots connect at once up front, so one slow login doesn't add to the others.
+async fn compare_servers(
+    args: &Args,
+    config: &config::Config,
+    servers: &[String],
+    test_files: &[PathBuf],
+    spacing: i32,
+    machine_output: bool,
+) -> Result<()> {
+    if args.watch || args.debug || args.batch.is_some() || args.repeat > 1 {
+        eprintln!(
+            "{} --watch, --debug, --batch and --repeat work with a single --server",
+            "Error:".red().bold()
+        );
+        std::process::exit(1);
+    }
+    let batch = match load_batch(test_files, 0, test_files.len(), spacing, machine_output) {
+        Ok(batch) => batch,
+        Err(e) => {
+            eprintln!("{} {:#}", "Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut executors = servers
+        .iter()
+        .map(|_| configure_executor(args, config, machine_output))
+        .collect::<Result<Vec<_>>>()?;
+    status!(
+        machine_output,
+        "{} Connecting to {} servers...",
+        "→".blue(),
+        servers.len()
+    );
+    let connections = futures::future::join_all(
+        executors
+            .iter_mut()
+            .zip(servers)
+            .map(|(executor, server)| executor.connect(server)),
+    )
+    .await;
+
+    // Results of every server, in the order of the batch's tests
+    let mut columns: Vec<Vec<flintcli::TestResult>> = Vec::new();
+    let mut versions: Vec<Option<String>> = Vec::new();
+    // Assertions of every test and server, named `test @ server` like the TAP points
+    let mut assertion_log: HashMap<String, Vec<flintcli::AssertionResult>> = HashMap::new();
+    let mut infra_failure = false;
+    for ((executor, server), connection) in executors.iter_mut().zip(servers).zip(connections) {
+        status!(
+            machine_output,
+            "\n{} {}",
+            "Server".cyan().bold(),
+            server.bold()
+        );
+        versions.push(executor.server_info().map(|info| info.version.clone()));
+        let outcome = match connection {
+            Err(e) => Err(e.context(format!("Could not connect to {}", server))),
+            Ok(()) => {
+                let run = tokio::select! {
+                    results = async {
+                        if args.warmup {
+                            warmup(executor, &batch.tests, &batch.extras, machine_output).await?;
+                        }
+                        executor.run_tests_parallel(&batch.tests, &batch.extras, false).await
+                    } => Some(results),
+                    _ = tokio::signal::ctrl_c() => None,
+                };
+                let Some(run) = run else {
+                    restore_after_interrupt(executor, "Interrupted").await;
+                    std::process::exit(130);
+                };
+                run.map_err(|e| e.context(format!("Run on {} failed", server)))
+            }
+        };
+        let results = outcome.unwrap_or_else(|e| {
+            eprintln!("{} {:#}", "Error:".red().bold(), e);
+            infra_failure = true;
+            batch
+                .tests
+                .iter()
+                .map(|(test, _)| {
+                    flintcli::TestResult::new(test.name.clone())
+                        .with_failure_reason(format!("{:#}", e))
+                })
+                .collect()
+        });
+        for (test, _) in &batch.tests {
+            assertion_log.insert(
+                format!("{} @ {}", test.name, server),
+                assertions_of(executor, &test.name),
+            );
+        }
+        columns.push(results);
+    }
+
+    status!(machine_output, "\n{}", "═".repeat(60).dimmed());
+    status!(machine_output, "{}", "Server Comparison".cyan().bold());
+    status!(machine_output, "{}", "═".repeat(60).dimmed());
+
+    let name_width = batch
+        .tests
+        .iter()
+        .map(|(test, _)| test.name.len())
+        .max()
+        .unwrap_or(0);
+    let column_widths: Vec<usize> = servers.iter().map(|server| server.len().max(4)).collect();
+    let header: Vec<String> = servers
+        .iter()
+        .zip(&column_widths)
+        .map(|(server, width)| format!("{:<width$}", server))
+        .collect();
+    status!(
+        machine_output,
+        "  {:<name_width$}  {}",
+        "",
+        header.join("  ").bold()
+    );
+
+    let mut differing = 0;
+    for (test_idx, (test, _)) in batch.tests.iter().enumerate() {
+        let outcomes: Vec<bool> = columns
+            .iter()
+            .map(|column| column[test_idx].success)
+            .collect();
+        let cells: Vec<String> = outcomes
+            .iter()
+            .zip(&column_widths)
+            .map(|(success, width)| {
+                let cell = format!("{:<width$}", if *success { "PASS" } else { "FAIL" });
+                if *success {
+                    cell.green().bold().to_string()
+                } else {
+                    cell.red().bold().to_string()
+                }
+            })
+            .collect();
+        // A test that passes somewhere but not everywhere points at a version difference
+        let differs = outcomes.iter().any(|success| *success) && outcomes.contains(&false);
+        if differs {
+            differing += 1;
+        }
+        status!(
+            machine_output,
+            "  {:<name_width$}  {}{}",
+            test.name,
+            cells.join("  "),
+            if differs {
+                format!("  {}", "differs".yellow().bold())
+            } else {
+                String::new()
+            }
+        );
+    }
+
+    status!(machine_output);
+    for ((server, column), version) in servers.iter().zip(&columns).zip(&versions) {
+        let passed = column.iter().filter(|r| r.success).count();
+        status!(
+            machine_output,
+            "  {} ({}): {} passed, {} failed",
+            server,
+            version.as_deref().unwrap_or("version unknown"),
+            passed.to_string().green(),
+            (column.len() - passed).to_string().red()
+        );
+    }
+    if differing > 0 {
+        status!(
+            machine_output,
+            "\n{} {} test(s) pass on some servers but fail on others",
+            "⚠".yellow().bold(),
+            differing
+        );
+    }
+
+    // One TAP test point per test and server, named `test @ server`
+    if args.tap || args.assertions.is_some() {
+        let mut results = Vec::new();
+        let mut details = HashMap::new();
+        for (server, column) in servers.iter().zip(&columns) {
+            for ((result, extras), (test, _)) in column.iter().zip(&batch.extras).zip(&batch.tests)
+            {
+                let name = format!("{} @ {}", test.name, server);
+                details.insert(name.clone(), report::TestDetails::from_extras(extras));
+                // Expected failures pass but keep their reason, so copy both
+                let mut copy = flintcli::TestResult::new(name);
+                if let Some(ref reason) = result.failure_reason {
+                    copy = copy.with_failure_reason(reason.clone());
+                } else if !result.success {
+                    copy = copy.with_failure_reason("failed".to_string());
+                }
+                copy.success = result.success;
+                results.push(copy);
+            }
+        }
+        if args.tap {
+            report::write_tap(&mut std::io::stdout(), &results, &details)?;
+            for (server, version) in servers.iter().zip(&versions) {
+                if let Some(version) = version {
+                    println!("# server {}: {}", server, version);
+                }
+            }
+        }
+        if let Some(ref path) = args.assertions {
+            write_assertions(path, &results, &assertion_log)?;
+        }
+    }
+
+    // A test that failed on any server counts as failed for --rerun-failed
+    let mut failed: Vec<cache::FailedTest> = Vec::new();
+    for (test_idx, ((test, _), file)) in batch.tests.iter().zip(&batch.files).enumerate() {
+        if columns.iter().any(|column| !column[test_idx].success) {
+            failed.push(cache::FailedTest {
+                name: test.name.clone(),
+                file: file.canonicalize().unwrap_or_else(|_| file.clone()),
+            });
+        }
+    }
+    if let Err(e) = (cache::ResultsCache { failed }).save(Path::new(cache::CACHE_FILE)) {
+        eprintln!("{} {:#}", "Warning:".yellow().bold(), e);
+    }
+
+    let total = columns.iter().map(Vec::len).sum::<usize>();
+    let total_passed = columns.iter().flatten().filter(|r| r.success).count();
+    status!(
+        machine_output,
+        "SUMMARY servers={} tests={} passed={} failed={} differing={}",
+        servers.len(),
+        total,
+        total_passed,
+        total - total_passed,
+        differing
+    );
+
+    if infra_failure {
+        std::process::exit(EXIT_INFRA_FAILURE);
+    }
+    if total_passed < total {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Run every group of tests on the server it is pinned to, one bot per server
+/// All bots connect at once up front, like `compare_servers`, and each group gets its
+/// own layout since it is built in its own world.
+async fn run_per_server(
+    args: &Args,
+    config: &config::Config,
+    groups: &[(String, Vec<PathBuf>)],
+    spacing: i32,
+    machine_output: bool,
+) -> Result<()> {
+    if args.watch || args.debug || args.batch.is_some() || args.repeat > 1 {
+        eprintln!(
+            "{} --watch, --debug, --batch and --repeat don't work with specs on several servers",
+            "Error:".red().bold()
+        );
+        std::process::exit(1);
+    }
+    let mut batches = Vec::new();
+    for (_, files) in groups {
+        match load_batch(files, 0, files.len(), spacing, machine_output) {
+            Ok(batch) => batches.push(batch),
+            Err(e) => {
+                eprintln!("{} {:#}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut executors = groups
+        .iter()
+        .map(|_| configure_executor(args, config, machine_output))
+        .collect::<Result<Vec<_>>>()?;
+    status!(
+        machine_output,
+        "{} Connecting to {} servers...",
+        "→".blue(),
+        groups.len()
+    );
+    let connections = futures::future::join_all(
+        executors
+            .iter_mut()
+            .zip(groups)
+            .map(|(executor, (server, _))| executor.connect(server)),
+    )
+    .await;
+
+    let mut results: Vec<flintcli::TestResult> = Vec::new();
+    let mut details: HashMap<String, report::TestDetails> = HashMap::new();
+    let mut versions: Vec<Option<String>> = Vec::new();
+    let mut failed: Vec<cache::FailedTest> = Vec::new();
+    let mut assertion_log: HashMap<String, Vec<flintcli::AssertionResult>> = HashMap::new();
+    let mut infra_failure = false;
+    for (((executor, (server, _)), batch), connection) in executors
+        .iter_mut()
+        .zip(groups)
+        .zip(&batches)
+        .zip(connections)
+    {
+        status!(
+            machine_output,
+            "\n{} {} ({} tests)",
+            "Server".cyan().bold(),
+            server.bold(),
+            batch.tests.len()
+        );
+        versions.push(executor.server_info().map(|info| info.version.clone()));
+        let outcome = match connection {
+            Err(e) => Err(e.context(format!("Could not connect to {}", server))),
+            Ok(()) => {
+                let run = tokio::select! {
+                    results = async {
+                        if args.warmup {
+                            warmup(executor, &batch.tests, &batch.extras, machine_output).await?;
+                        }
+                        executor.run_tests_parallel(&batch.tests, &batch.extras, false).await
+                    } => Some(results),
+                    _ = tokio::signal::ctrl_c() => None,
+                };
+                let Some(run) = run else {
+                    restore_after_interrupt(executor, "Interrupted").await;
+                    std::process::exit(130);
+                };
+                run.map_err(|e| e.context(format!("Run on {} failed", server)))
+            }
+        };
+        let group_results = outcome.unwrap_or_else(|e| {
+            eprintln!("{} {:#}", "Error:".red().bold(), e);
+            infra_failure = true;
+            batch
+                .tests
+                .iter()
+                .map(|(test, _)| {
+                    flintcli::TestResult::new(test.name.clone())
+                        .with_failure_reason(format!("{:#}", e))
+                })
+                .collect()
+        });
+
+        for ((result, extras), file) in group_results.iter().zip(&batch.extras).zip(&batch.files) {
+            let mut test_details = report::TestDetails::from_extras(extras);
+            test_details.server = Some(server.clone());
+            details.insert(result.test_name.clone(), test_details);
+            assertion_log.insert(
+                result.test_name.clone(),
+                assertions_of(executor, &result.test_name),
+            );
+            if !result.success {
+                failed.push(cache::FailedTest {
+                    name: result.test_name.clone(),
+                    file: file.canonicalize().unwrap_or_else(|_| file.clone()),
+                });
+            }
+        }
+        results.extend(group_results);
+    }
+
+    status!(machine_output, "\n{}", "═".repeat(60).dimmed());
+    status!(machine_output, "{}", "Test Summary".cyan().bold());
+    status!(machine_output, "{}", "═".repeat(60).dimmed());
+
+    let is_expected_failure = |result: &flintcli::TestResult| {
+        details
+            .get(&result.test_name)
+            .is_some_and(|details| details.is_expected_failure(result))
+    };
+    for result in &results {
+        let status = if is_expected_failure(result) {
+            "XFAIL".green().bold()
+        } else if result.success {
+            "PASS".green().bold()
+        } else {
+            "FAIL".red().bold()
+        };
+        let server = details
+            .get(&result.test_name)
+            .and_then(|details| details.server.as_deref())
+            .unwrap_or_default();
+        status!(
+            machine_output,
+            "  [{}] {} {}",
+            status,
+            result.test_name,
+            format!("@ {}", server).dimmed()
+        );
+    }
+
+    status!(machine_output);
+    let mut first = 0;
+    for (((server, _), batch), version) in groups.iter().zip(&batches).zip(&versions) {
+        let group_results = &results[first..first + batch.tests.len()];
+        first += batch.tests.len();
+        let passed = group_results.iter().filter(|r| r.success).count();
+        status!(
+            machine_output,
+            "  {} ({}): {} passed, {} failed",
+            server,
+            version.as_deref().unwrap_or("version unknown"),
+            passed.to_string().green(),
+            (group_results.len() - passed).to_string().red()
+        );
+    }
+
+    if args.tap {
+        report::write_tap(&mut std::io::stdout(), &results, &details)?;
+        for ((server, _), version) in groups.iter().zip(&versions) {
+            if let Some(version) = version {
+                println!("# server {}: {}", server, version);
+            }
+        }
+    }
+    if let Some(ref path) = args.assertions {
+        write_assertions(path, &results, &assertion_log)?;
+    }
+
+    if let Err(e) = (cache::ResultsCache { failed }).save(Path::new(cache::CACHE_FILE)) {
+        eprintln!("{} {:#}", "Warning:".yellow().bold(), e);
+    }
+
+    let total_passed = results.iter().filter(|r| r.success).count();
+    let expected_failures = results
+        .iter()
+        .filter(|&result| is_expected_failure(result))
+        .count();
+    let xfail_note = if expected_failures > 0 {
+        format!(" xfail={}", expected_failures)
+    } else {
+        String::new()
+    };
+    status!(
+        machine_output,
+        "SUMMARY servers={} tests={} passed={} failed={}{}",
+        groups.len(),
+        results.len(),
+        total_passed,
+        results.len() - total_passed,
+        xfail_note
+    );
+
+    if infra_failure {
+        std::process::exit(EXIT_INFRA_FAILURE);
+    }
+    if total_passed < results.len() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Assertions the executor recorded for one test in its most recent run
+fn assertions_of(
+    executor: &executor::TestExecutor,
+    test_name: &str,
+) -> Vec<flintcli::AssertionResult> {
+    executor
+        .assertion_results()
+        .iter()
+        .filter(|assertion| assertion.test_name == test_name)
+        .cloned()
+        .collect()
+}
+
+/// Write the `--assertions` report to a file
+fn write_assertions(
+    path: &Path,
+    results: &[flintcli::TestResult],
+    assertions: &HashMap<String, Vec<flintcli::AssertionResult>>,
+) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = std::io::BufWriter::new(file);
+    report::write_assertions(&mut out, results, assertions)
+        .and_then(|()| std::io::Write::flush(&mut out))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Run the first test once and throw its result away
+/// The first run after connecting can fail while chunks and the command pipeline are
+/// still catching up; warming up with a real test keeps that out of the results.
+async fn warmup(
+    executor: &mut executor::TestExecutor,
+    tests: &[(TestSpec, [i32; 3])],
+    extras: &[spec::SpecExtras],
+    machine_output: bool,
+) -> Result<()> {
+    let (Some(test), Some(test_extras)) = (tests.first(), extras.first()) else {
+        return Ok(());
+    };
+    status!(
+        machine_output,
+        "{} Warmup: running {} once, its result is discarded",
+        "→".blue(),
+        test.0.name
+    );
+    let results = executor
+        .run_tests_parallel(
+            std::slice::from_ref(test),
+            std::slice::from_ref(test_extras),
+            false,
+        )
+        .await?;
+    let outcome = if results.iter().all(|result| result.success) {
+        "passed"
+    } else {
+        "failed"
+    };
+    status!(
+        machine_output,
+        "{} Warmup done ({} {}, not counted)\n",
+        "✓".green(),
+        test.0.name,
+        outcome
+    );
+    Ok(())
+}
+
+/// Gap between automatically arranged test areas: flag > config file > built-in default
+fn layout_spacing(args: &Args, config: &config::Config) -> i32 {
+    args.spacing
+        .or(config.spacing)
+        .unwrap_or(layout::DEFAULT_SPACING)
+}
+
+/// Build an executor with every setting from the flags and the config file applied
+fn configure_executor(
+    args: &Args,
+    config: &config::Config,
+    machine_output: bool,
+) -> Result<executor::TestExecutor> {
+    let mut executor = executor::TestExecutor::new();
+    executor.set_machine_output(machine_output);
+    // Every run lays its tests out on the grid, so nothing else is built in its gaps
+    executor.set_cleanup_gap(layout_spacing(args, config));
+
+    // Enable chat control if requested
+    if args.chat_control {
+        executor.set_chat_control(true);
+        status!(
+            machine_output,
+            "{} Chat control enabled - you can type 's' or 'c' in game chat",
+            "→".yellow()
+        );
+    }
+
+    if args.quiet {
+        executor.set_quiet(true);
+    }
+
+    if args.verbose_failures {
+        executor.set_verbose_failures(true);
+    }
+
+    if args.editor {
+        executor.set_editor_output(true);
+    }
+
+    if let Some(flavor) = args.server_flavor.or(config.server_flavor) {
+        executor.set_server_flavor(flavor);
+    }
+
+    if let Some(username) = args.username.clone().or(config.username.clone()) {
+        executor.set_username(username);
+    }
+
+    if args.random_suffix {
+        executor.randomize_username();
+    }
+
+    if let Some(tick_delay_ms) = config.tick_delay_ms {
+        executor.set_tick_delay(tick_delay_ms);
+    }
+
+    if let Some(place_delay_ms) = config.place_delay_ms {
+        executor.set_place_delay(place_delay_ms);
+    }
+
+    if let Some(ref aliases) = config.block_aliases {
+        executor.set_block_aliases(aliases.clone());
+    }
+
+    if let Some(ref rules) = config.block_transforms {
+        executor.set_block_transforms(
+            rules
+                .iter()
+                .map(|rule| flintcli::blocks::BlockTransform::new(&rule.pattern, &rule.replace))
+                .collect::<Result<_>>()?,
+        );
+    }
+
+    if let Some(max_burst) = args.max_burst.or(config.max_burst) {
+        executor.set_max_burst(max_burst);
+    }
+
+    if let Some(secs) = args.init_timeout.or(config.init_timeout_secs) {
+        executor.set_init_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = args.join_timeout.or(config.join_timeout_secs) {
+        executor.set_join_timeout(Duration::from_secs(secs));
+    }
+
+    if let Some(per_second) = config.command_rate {
+        executor.set_command_rate(per_second);
+    }
+
+    if config.return_when_queued == Some(true) {
+        executor.set_return_when_queued(true);
+    }
+
+    if let Some(timeout_ms) = config.world_load_timeout_ms {
+        executor.set_world_load_timeout(timeout_ms);
+    }
+
+    if args.no_connect_commands {
+        executor.set_connect_commands(Vec::new());
+    } else if let Some(ref commands) = config.connect_commands {
+        executor.set_connect_commands(commands.clone());
+    }
+
+    if let Some(seed) = args.seed {
+        executor.set_seed(seed);
+    }
+
+    if let Some(ref commands) = config.seed_commands {
+        executor.set_seed_commands(commands.clone());
+    }
+
+    if args.no_teleport {
+        executor.set_teleport(false);
+    }
+
+    if args.strict {
+        executor.set_strict(true);
+    }
+
+    if args.heartbeat {
+        executor.set_heartbeat(true);
+    }
+
+    if let Some(ref path) = args.trace {
+        executor.set_trace(path)?;
+    }
+
+    if let Some(tick) = args.stop_at_tick {
+        executor.set_stop_at_tick(tick);
+    }
+
+    if args.keep_on_failure {
+        executor.set_keep_on_failure(true);
+    }
+
+    if args.abort_on_setup_error {
+        executor.set_abort_on_setup_error(true);
+    }
+
+    if args.debug {
+        executor.set_snapshot(true);
+    }
+
+    if let Some(ref suite_path) = args.suite {
+        executor.set_suite(suite::Suite::from_file(suite_path)?);
+        status!(
+            machine_output,
+            "{} Using suite {}",
+            "→".blue(),
+            suite_path.display()
+        );
+    }
+
+    Ok(executor)
+}
+
+/// Specs of one batch, laid out and ready to run
+struct Batch {
+    tests: Vec<(TestSpec, [i32; 3])>,
+    extras: Vec<spec::SpecExtras>,
+    /// Spec file of every test; a spec with `instances` has one test per instance
+    files: Vec<PathBuf>,
+}
+
+/// Load the specs of one batch and arrange them in a grid so their areas never overlap
+/// Every instance of a spec with `instances` gets its own cell, moved by its shift.
+/// `first_index` is the position of the batch's first file among all `total_tests`.
+fn load_batch(
+    files: &[PathBuf],
+    first_index: usize,
+    total_tests: usize,
+    spacing: i32,
+    machine_output: bool,
+) -> Result<Batch> {
+    let mut tests = Vec::new();
+    let mut extras = Vec::new();
+    let mut test_files = Vec::new();
+    // File index and instance (index, shift) of every test
+    let mut origins: Vec<(usize, Option<(usize, [i32; 3])>)> = Vec::new();
+
+    for (file_index, test_file) in files.iter().enumerate() {
+        let load = || {
+            spec::load_spec(test_file)
+                .with_context(|| format!("Failed to load test {}", test_file.display()))
+        };
+        let (test, test_extras) = load()?;
+        if test_extras.instances.is_empty() {
+            tests.push(test);
+            extras.push(test_extras);
+            test_files.push(test_file.clone());
+            origins.push((file_index, None));
+            continue;
+        }
+
+        // Every instance is loaded separately, since a TestSpec cannot be copied
+        for (instance, shift) in test_extras.instances.iter().enumerate() {
+            let (mut instance_test, instance_extras) = load()?;
+            instance_test.name = format!("{}#{}", test.name, instance);
+            tests.push(instance_test);
+            extras.push(instance_extras);
+            test_files.push(test_file.clone());
+            origins.push((file_index, Some((instance, *shift))));
+        }
+    }
+
+    // Laid out relative to each spec's origin, which the executor subtracts again
+    let regions: Vec<[[i32; 3]; 2]> = tests
+        .iter()
+        .zip(&extras)
+        .zip(&origins)
+        .map(|((test, test_extras), (_, instance))| {
+            let region = layout::relative_to(test.cleanup_region(), test_extras.origin);
+            match instance {
+                Some((_, shift)) => layout::with_shift(region, *shift),
+                None => region,
+            }
+        })
+        .collect();
+    let offsets = layout::compute_region_offsets(&regions, spacing);
+
+    let mut tests_with_offsets = Vec::new();
+    for (((test, offset), test_extras), (file_index, instance)) in
+        tests.into_iter().zip(offsets).zip(&extras).zip(origins)
+    {
+        let mut offset = offset;
+        let label = match instance {
+            Some((index, shift)) => {
+                offset = [0, 1, 2].map(|axis| offset[axis] + shift[axis]);
+                format!(" #{}", index)
+            }
+            None => String::new(),
+        };
+        let world_offset = layout::apply_origin(offset, test_extras.origin);
+        status!(
+            machine_output,
+            "  {} Grid position: {}{} (offset: [{}, {}, {}])",
+            "→".blue(),
+            format!("[{}/{}]", first_index + file_index + 1, total_tests).dimmed(),
+            label,
+            world_offset[0],
+            world_offset[1],
+            world_offset[2]
+        );
+        tests_with_offsets.push((test, offset));
+    }
+    Ok(Batch {
+        tests: tests_with_offsets,
+        extras,
+        files: test_files,
+    })
+}
+
+/// Re-run the specs under `path` whenever they change, keeping the bot connected
+/// Each run only includes the changed specs, cleaned before and after as usual.
+/// Returns on Ctrl-C between runs; Ctrl-C during a run cleans up and exits.
+async fn watch_specs(
+    executor: &mut executor::TestExecutor,
+    path: &Path,
+    recursive: bool,
+    spacing: i32,
+    machine_output: bool,
+) -> Result<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && (event.kind.is_create() || event.kind.is_modify())
+        {
+            let _ = tx.send(event.paths);
+        }
+    })
+    .context("Failed to start the file watcher")?;
+
+    // A single spec is watched through its directory, since many editors save by
+    // writing a new file and renaming it over the old one
+    let (watched, mode) = if path.is_dir() {
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        (path, mode)
+    } else {
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        (parent, RecursiveMode::NonRecursive)
+    };
+    watcher
+        .watch(watched, mode)
+        .with_context(|| format!("Failed to watch {}", watched.display()))?;
+
+    let mut run = 0;
+    loop {
+        status!(
+            machine_output,
+            "{} Watching {} for changes (Ctrl-C to stop)",
+            "→".blue(),
+            path.display()
+        );
+        let mut changed: Vec<PathBuf> = tokio::select! {
+            paths = rx.recv() => paths.context("File watcher stopped")?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        while let Ok(Some(paths)) = tokio::time::timeout(WATCH_DEBOUNCE, rx.recv()).await {
+            changed.extend(paths);
+        }
+        let changed: HashSet<PathBuf> = changed
+            .iter()
+            .filter_map(|file| file.canonicalize().ok())
+            .collect();
+
+        // Collected again so specs created since the last run are picked up
+        let test_files: Vec<PathBuf> = match TestLoader::collect_test_files(path, recursive) {
+            Ok(files) => files
+                .into_iter()
+                .filter(|file| {
+                    file.canonicalize()
+                        .is_ok_and(|file| changed.contains(&file))
+                })
+                .collect(),
+            Err(e) => {
+                eprintln!("{} {:#}", "Warning:".yellow().bold(), e);
+                continue;
+            }
+        };
+        if test_files.is_empty() {
+            continue;
+        }
+
+        run += 1;
+        status!(
+            machine_output,
+            "\n{} {} {}",
+            "═".repeat(20).dimmed(),
+            format!("Watch run {}: {} changed spec(s)", run, test_files.len())
+                .cyan()
+                .bold(),
+            "═".repeat(20).dimmed()
+        );
+        // A spec caught mid-edit fails to load; the next save triggers another run
+        let Batch {
+            tests: tests_with_offsets,
+            extras,
+            ..
+        } = match load_batch(&test_files, 0, test_files.len(), spacing, machine_output) {
+            Ok(batch) => batch,
+            Err(e) => {
+                eprintln!("{} {:#}\n", "Error:".red().bold(), e);
+                continue;
+            }
+        };
+        status!(machine_output);
+
+        let results = tokio::select! {
+            results = executor.run_tests_parallel(&tests_with_offsets, &extras, false) => results?,
+            _ = tokio::signal::ctrl_c() => {
+                restore_after_interrupt(executor, "Interrupted").await;
+                std::process::exit(130);
+            }
+        };
+
+        status!(machine_output);
+        for result in &results {
+            let status = if result.success {
+                "PASS".green().bold()
+            } else {
+                "FAIL".red().bold()
+            };
+            status!(machine_output, "  [{}] {}", status, result.test_name);
+        }
+        let passed = results.iter().filter(|r| r.success).count();
+        status!(
+            machine_output,
+            "\n{} tests run: {} passed, {} failed\n",
+            results.len(),
+            passed.to_string().green(),
+            (results.len() - passed).to_string().red()
+        );
+    }
+}
+
+/// Wait for the `--timeout` deadline, or forever without one
+async fn deadline_reached(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Unfreeze time and clear the test areas of a run cut short, e.g. by Ctrl-C or
+/// `--timeout`; a Ctrl-C during the cleanup skips it
+async fn restore_after_interrupt(executor: &mut executor::TestExecutor, cause: &str) {
+    eprintln!(
+        "\n{} {}, unfreezing time and clearing test areas (Ctrl-C again to skip)...",
+        "⚠".yellow().bold(),
+        cause
+    );
+    tokio::select! {
+        restored = executor.restore_after_interrupt() => match restored {
+            Ok(regions) => eprintln!(
+                "{} Cleanup ran: time unfrozen and {} test region(s) cleaned",
+                "✓".green(),
+                regions
+            ),
+            Err(e) => eprintln!(
+                "{} Cleanup failed, the world may still be frozen or hold test blocks: {}",
+                "Error:".red().bold(),
+                e
+            ),
+        },
+        _ = tokio::signal::ctrl_c() => eprintln!(
+            "{} Cleanup skipped, the world may still be frozen or hold test blocks",
+            "Warning:".yellow().bold()
+        ),
+    }
+}
+
+/// Replay the tests from their tick-0 snapshot as often as asked, then remove the snapshot
+async fn debug_replays(
+    executor: &mut executor::TestExecutor,
+    tests_with_offsets: &[(TestSpec, [i32; 3])],
+    extras: &[spec::SpecExtras],
+    machine_output: bool,
+) -> Result<()> {
+    loop {
+        status!(
+            machine_output,
+            "\n{} {} = replay from tick 0, {} = replay and break after setup, {} = quit",
+            "⏸".yellow().bold(),
+            "r".cyan().bold(),
+            "b".cyan().bold(),
+            "q".cyan().bold()
+        );
+        // The prompt stays off stdout too, so a TAP report there is not interleaved
+        if machine_output {
+            eprint!("  > ");
+            std::io::Write::flush(&mut std::io::stderr())?;
+        } else {
+            print!("  > ");
+            std::io::Write::flush(&mut std::io::stdout())?;
+        }
+
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input)? == 0 {
+            break;
+        }
+        let break_after_setup = match input.trim().to_lowercase().as_str() {
+            "r" | "replay" => false,
+            "b" | "break" => true,
+            "q" | "quit" => break,
+            other => {
+                status!(machine_output, "  Unknown command '{}'", other);
+                continue;
+            }
+        };
+
+        let results = executor
+            .replay(tests_with_offsets, extras, break_after_setup)
+            .await?;
+        let passed = results.iter().filter(|r| r.success).count();
+        status!(
+            machine_output,
+            "\n{} Replay: {} passed, {} failed",
+            "→".blue().bold(),
+            passed.to_string().green(),
+            (results.len() - passed).to_string().red()
+        );
+    }
+    executor.clear_snapshot().await?;
+    Ok(())
+}
+
+/// Print every spec under `path` sorted by name, with its last tick and cleanup volume
+fn list_specs(path: &Path, recursive: bool) -> Result<()> {
+    let test_files = TestLoader::collect_test_files(path, recursive)?;
+    let mut specs = Vec::new();
+    for test_file in &test_files {
+        match spec::spec_info(test_file) {
+            Ok(info) => specs.push(info),
+            Err(e) => eprintln!("  {} {}: {:#}", "✗".red().bold(), test_file.display(), e),
+        }
+    }
+    specs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let name_width = specs.iter().map(|info| info.name.len()).max().unwrap_or(0);
+    println!(
+        "  {:<name_width$}  {:>6}  {:>9}  {}",
+        "TEST".bold(),
+        "TICKS".bold(),
+        "CLEANUP".bold(),
+        "DESCRIPTION".bold()
+    );
+    for info in &specs {
+        // Cleanups beyond a single /fill are worth a second look
+        let volume = format!("{:>9}", info.cleanup_volume);
+        let volume = if info.cleanup_volume > layout::MAX_FILL_VOLUME {
+            volume.yellow().bold()
+        } else {
+            volume.normal()
+        };
+        println!(
+            "  {:<name_width$}  {:>6}  {}  {}",
+            info.name,
+            info.max_tick,
+            volume,
+            info.description.as_deref().unwrap_or("").dimmed()
+        );
+    }
+
+    let total_ticks: u64 = specs.iter().map(|info| u64::from(info.max_tick)).sum();
+    let longest = specs.iter().map(|info| info.max_tick).max().unwrap_or(0);
+    println!(
+        "\n{} test(s): {} ticks in total, longest timeline {} ticks",
+        specs.len(),
+        total_ticks,
+        longest
+    );
+    if specs.len() < test_files.len() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Read a TAP report from a file, or from stdin for `-`
+fn read_report(path: &Path) -> Result<Vec<(String, bool)>> {
+    let content = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read report from stdin")?
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read report {}", path.display()))?
+    };
+    let results = report::read_tap(&content);
+    if results.is_empty() && !content.contains("1..0") {
+        anyhow::bail!("{} has no TAP test points", path.display());
+    }
+    Ok(results)
+}
+
+fn compare_reports(baseline: &Path, current: &Path) -> Result<()> {
+    let comparison = report::Comparison::new(&read_report(baseline)?, &read_report(current)?);
+
+    for name in &comparison.regressions {
+        println!("  [{}] {}", "REGRESSION".red().bold(), name);
+    }
+    for name in &comparison.new_failures {
+        println!(
+            "  [{}] {} (not in the baseline)",
+            "NEW FAIL".red().bold(),
+            name
+        );
+    }
+    for name in &comparison.fixes {
+        println!("  [{}] {}", "FIXED".green().bold(), name);
+    }
+    for name in &comparison.removed {
+        println!("  [{}] {}", "REMOVED".dimmed(), name);
+    }
+
+    let failures = comparison.regressions.len() + comparison.new_failures.len();
+    println!(
+        "\n{} regression(s), {} new failure(s), {} fix(es), {} unchanged, {} removed",
+        comparison.regressions.len().to_string().red(),
+        comparison.new_failures.len().to_string().red(),
+        comparison.fixes.len().to_string().green(),
+        comparison.unchanged,
+        comparison.removed.len()
+    );
+    println!(
+        "COMPARE regressions={} new_failures={} fixes={} unchanged={} removed={}",
+        comparison.regressions.len(),
+        comparison.new_failures.len(),
+        comparison.fixes.len(),
+        comparison.unchanged,
+        comparison.removed.len()
+    );
+
+    if failures > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Scaffold an example spec and a starter config
+fn init(path: &Path, force: bool) -> Result<()> {
+    let spec_path = scaffold::write_spec(path, force)?;
+    println!("  {} Wrote {}", "✓".green(), spec_path.display());
+    if scaffold::write_config(force)? {
+        println!("  {} Wrote {}", "✓".green(), config::DEFAULT_CONFIG_FILE);
+    } else {
+        println!(
+            "  {} Kept existing {}",
+            "→".blue(),
+            config::DEFAULT_CONFIG_FILE
+        );
+    }
+    println!(
+        "\nSet `server` in {} and run: flintmc {}",
+        config::DEFAULT_CONFIG_FILE,
+        spec_path.display()
+    );
+    Ok(())
+}
+
+/// Validate every spec under `path` and exit non-zero if any is invalid
+fn validate_specs(path: &Path, recursive: bool) -> Result<()> {
+    let test_files = TestLoader::collect_test_files(path, recursive)?;
+    if test_files.is_empty() {
+        eprintln!(
+            "{} No test files found at: {}",
+            "Error:".red().bold(),
+            path.display()
+        );
+        std::process::exit(1);
+    }
+
+    let mut invalid = 0;
+    for test_file in &test_files {
+        let report = validate::validate_spec(test_file);
+        let name = report
+            .test_name
+            .as_deref()
+            .map(|name| format!(" [{}]", name))
+            .unwrap_or_default();
+        if report.is_valid() {
+            println!("  {} {}{}", "✓".green(), test_file.display(), name);
+        } else {
+            invalid += 1;
+            println!("  {} {}{}", "✗".red().bold(), test_file.display(), name);
+            for problem in &report.problems {
+                println!("      {}", problem.red());
+            }
+        }
+    }
+
+    println!(
+        "\n{} spec(s) checked: {} valid, {} invalid",
+        test_files.len(),
+        (test_files.len() - invalid).to_string().green(),
+        invalid.to_string().red()
+    );
+
+    if invalid > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Fisher-Yates shuffle driven by splitmix64, so a seed gives the same order everywhere
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// Canonical paths of files changed since `since`: committed, staged, unstaged or untracked
+fn changed_files(since: &str) -> Result<HashSet<PathBuf>> {
+    if since.starts_with('-') {
+        anyhow::bail!("'{}' is not a git ref", since);
+    }
+    let root = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+    let changed = git(&["diff", "--name-only", since, "--"])?;
+    let untracked = git(&["ls-files", "--others", "--exclude-standard", "--full-name"])?;
+
+    Ok(changed
+        .lines()
+        .chain(untracked.lines())
+        .filter_map(|name| root.join(name).canonicalize().ok())
+        .collect())
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .output()
+        .context("git is not available")?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shuffled(len: usize, seed: u64) -> Vec<usize> {
+        let mut items: Vec<usize> = (0..len).collect();
+        shuffle(&mut items, seed);
+        items
+    }
+
+    #[test]
+    fn shuffle_order_is_pinned_per_seed() {
+        // A changed order breaks `--shuffle SEED` reproductions from older runs
+        assert_eq!(shuffled(10, 42), [0, 9, 5, 8, 6, 4, 7, 2, 1, 3]);
+        assert_eq!(shuffled(10, 43), [4, 2, 5, 6, 1, 3, 9, 8, 7, 0]);
+        assert_eq!(shuffled(10, 0), [6, 3, 2, 9, 8, 1, 4, 7, 0, 5]);
+    }
+
+    #[test]
+    fn shuffle_keeps_every_item() {
+        let mut items = shuffled(100, 7);
+        items.sort_unstable();
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+        assert_eq!(shuffled(0, 7), Vec::<usize>::new());
+        assert_eq!(shuffled(1, 7), [0]);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::executor::AssertionResult;
+use crate::spec::SpecExtras;
+use flint_core::results::TestResult;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+/// Spec fields carried into reports next to a test's result
+#[derive(Debug, Clone, Default)]
+pub struct TestDetails {
+    pub description: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+    /// The spec is marked `expect_fail`, so a passing result is an expected failure
+    pub expect_fail: bool,
+    /// Server the test ran on, when a run spans several servers
+    pub server: Option<String>,
+}
+
+impl TestDetails {
+    pub fn from_extras(extras: &SpecExtras) -> Self {
+        Self {
+            description: extras.description.clone(),
+            metadata: extras.metadata.clone(),
+            expect_fail: extras.expect_fail,
+            server: None,
+        }
+    }
+
+    /// Whether a result is a test marked `expect_fail` that failed as expected
+    pub fn is_expected_failure(&self, result: &TestResult) -> bool {
+        self.expect_fail && result.success
+    }
+
+    fn is_empty(&self) -> bool {
+        self.description.is_none() && self.metadata.is_empty() && self.server.is_none()
+    }
+
+    /// YAML lines for a TAP diagnostic block
+    fn write_yaml(&self, out: &mut impl Write) -> io::Result<()> {
+        if let Some(ref description) = self.description {
+            writeln!(out, "  description: {}", yaml_string(description))?;
+        }
+        if let Some(ref server) = self.server {
+            writeln!(out, "  server: {}", yaml_string(server))?;
+        }
+        if !self.metadata.is_empty() {
+            writeln!(out, "  metadata:")?;
+            for (key, value) in &self.metadata {
+                writeln!(out, "    {}: {}", yaml_string(key), yaml_string(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One test's entry in the `--assertions` report
+#[derive(Serialize)]
+struct TestAssertions<'a> {
+    test_name: &'a str,
+    success: bool,
+    assertions: &'a [AssertionResult],
+}
+
+/// Write every test's result with its individual assertions as a JSON array
+/// Tests without recorded assertions, e.g. ones that never ran, get an empty list.
+pub fn write_assertions(
+    out: &mut impl Write,
+    results: &[TestResult],
+    assertions: &HashMap<String, Vec<AssertionResult>>,
+) -> io::Result<()> {
+    let tests: Vec<TestAssertions> = results
+        .iter()
+        .map(|result| TestAssertions {
+            test_name: &result.test_name,
+            success: result.success,
+            assertions: assertions
+                .get(&result.test_name)
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+        })
+        .collect();
+    serde_json::to_writer_pretty(&mut *out, &tests)?;
+    writeln!(out)
+}
+
+/// How the results of two runs differ, by test name
+#[derive(Debug, Default)]
+pub struct Comparison {
+    /// Passed in the baseline, failing now
+    pub regressions: Vec<String>,
+    /// Not in the baseline and failing now
+    pub new_failures: Vec<String>,
+    /// Failed in the baseline, passing now
+    pub fixes: Vec<String>,
+    /// Same result in both runs
+    pub unchanged: usize,
+    /// In the baseline but not in the current run
+    pub removed: Vec<String>,
+}
+
+impl Comparison {
+    /// Compare two runs given as (test name, passed) in report order
+    pub fn new(baseline: &[(String, bool)], current: &[(String, bool)]) -> Self {
+        let before: HashMap<&str, bool> = baseline
+            .iter()
+            .map(|(name, passed)| (name.as_str(), *passed))
+            .collect();
+        let mut comparison = Self::default();
+        for (name, passed) in current {
+            match (before.get(name.as_str()), passed) {
+                (Some(true), false) => comparison.regressions.push(name.clone()),
+                (None, false) => comparison.new_failures.push(name.clone()),
+                (Some(false), true) => comparison.fixes.push(name.clone()),
+                _ => comparison.unchanged += 1,
+            }
+        }
+        let now: HashMap<&str, bool> = current
+            .iter()
+            .map(|(name, passed)| (name.as_str(), *passed))
+            .collect();
+        comparison.removed = baseline
+            .iter()
+            .filter(|(name, _)| !now.contains_key(name.as_str()))
+            .map(|(name, _)| name.clone())
+            .collect();
+        comparison
+    }
+}
+
+/// Test points of a TAP report as (test name, passed), in report order
+/// Points with a TODO directive, such as expected failures, count as passed.
+pub fn read_tap(content: &str) -> Vec<(String, bool)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (passed, rest) = match line.strip_prefix("not ok") {
+                Some(rest) => (false, rest),
+                None => (true, line.strip_prefix("ok")?),
+            };
+            if !rest.is_empty() && !rest.starts_with(' ') {
+                return None;
+            }
+            // `ok 3 - name # TODO reason`: the number and dash are optional
+            let rest = rest
+                .trim_start()
+                .trim_start_matches(|c: char| c.is_ascii_digit());
+            let rest = rest.trim_start();
+            let rest = rest.strip_prefix('-').unwrap_or(rest).trim_start();
+            let (name, todo) = match rest.rsplit_once(" # ") {
+                Some((name, directive))
+                    if directive.to_ascii_uppercase().starts_with("TODO")
+                        || directive.to_ascii_uppercase().starts_with("SKIP") =>
+                {
+                    (name, directive.to_ascii_uppercase().starts_with("TODO"))
+                }
+                _ => (rest, false),
+            };
+            Some((name.trim_end().to_string(), passed || todo))
+        })
+        .collect()
+}
+
+/// Split a failure reason into its summary line and the individual failure messages
+/// The executor writes one message per line after the summary; continuation lines
+/// of a multi-line message are indented.
+fn failure_details(reason: &str) -> (&str, Vec<String>) {
+    let mut lines = reason.lines();
+    let summary = lines.next().unwrap_or_default();
+
+    let mut failures: Vec<String> = Vec::new();
+    for line in lines {
+        match failures.last_mut() {
+            Some(last) if line.starts_with(char::is_whitespace) => {
+                last.push('\n');
+                last.push_str(line);
+            }
+            _ => failures.push(line.to_string()),
+        }
+    }
+    (summary, failures)
+}
+
+/// Quote a string as a YAML scalar (JSON strings are valid YAML)
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// Write results as TAP version 13, with a YAML diagnostic block for each failure
+/// Tests with a description or metadata in `details`, keyed by test name, get a
+/// block with them even when they pass. Expected failures are `not ok` with a TODO
+/// directive, which TAP consumers don't count as failures.
+pub fn write_tap(
+    out: &mut impl Write,
+    results: &[TestResult],
+    details: &HashMap<String, TestDetails>,
+) -> io::Result<()> {
+    writeln!(out, "TAP version 13")?;
+    writeln!(out, "1..{}", results.len())?;
+
+    for (index, result) in results.iter().enumerate() {
+        let details = details.get(&result.test_name);
+        let expected_failure = details.is_some_and(|details| details.is_expected_failure(result));
+        let details = details.filter(|details| !details.is_empty());
+        if result.success && !expected_failure {
+            writeln!(out, "ok {} - {}", index + 1, result.test_name)?;
+            if let Some(details) = details {
+                writeln!(out, "  ---")?;
+                details.write_yaml(out)?;
+                writeln!(out, "  ...")?;
+            }
+            continue;
+        }
+
+        let directive = if expected_failure {
+            " # TODO expected failure"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "not ok {} - {}{}",
+            index + 1,
+            result.test_name,
+            directive
+        )?;
+        let reason = result.failure_reason.as_deref().unwrap_or("failed");
+        let (summary, failures) = failure_details(reason);
+        writeln!(out, "  ---")?;
+        writeln!(out, "  message: {}", yaml_string(summary))?;
+        let severity = if expected_failure { "todo" } else { "fail" };
+        writeln!(out, "  severity: {}", severity)?;
+        if let Some(details) = details {
+            details.write_yaml(out)?;
+        }
+        if !failures.is_empty() {
+            writeln!(out, "  failures:")?;
+            for failure in &failures {
+                writeln!(out, "    - {}", yaml_string(failure))?;
+            }
+        }
+        writeln!(out, "  ...")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(points: &[(&str, bool)]) -> Vec<(String, bool)> {
+        points
+            .iter()
+            .map(|(name, passed)| (name.to_string(), *passed))
+            .collect()
+    }
+
+    #[test]
+    fn read_tap_counts_todo_points_as_passed() {
+        let report = "TAP version 13\n\
+            1..4\n\
+            ok 1 - places_stone\n\
+            not ok 2 - piston_door # TODO expected failure\n\
+            not ok 3 - hopper_clock # todo flaky on paper\n\
+            not ok 4 - comparator_chain # SKIP no server\n";
+        assert_eq!(
+            read_tap(report),
+            points(&[
+                ("places_stone", true),
+                ("piston_door", true),
+                ("hopper_clock", true),
+                ("comparator_chain", false),
+            ])
+        );
+    }
+
+    #[test]
+    fn read_tap_accepts_points_without_numbers_or_dashes() {
+        let report = "ok - first\n\
+            not ok second\n\
+            ok 7 third\n\
+            not ok\n";
+        assert_eq!(
+            read_tap(report),
+            points(&[
+                ("first", true),
+                ("second", false),
+                ("third", true),
+                ("", false)
+            ])
+        );
+    }
+
+    #[test]
+    fn read_tap_skips_everything_but_test_points() {
+        let report = "TAP version 13\n\
+            1..1\n\
+            # server: Paper 1.21.4\n\
+            okay this is not a point\n\
+            not ok 1 - lamp\n  \
+              ---\n  \
+              message: \"1 assertions failed\"\n  \
+              failures:\n    \
+                - \"not ok in a yaml block\"\n  \
+              ...\n";
+        assert_eq!(read_tap(report), points(&[("lamp", false)]));
+    }
+
+    #[test]
+    fn expected_failures_read_back_as_passed() {
+        let mut xfail = TestResult::new("piston_door".to_string())
+            .with_failure_reason("1 assertions failed".to_string());
+        xfail.success = true;
+        let details = HashMap::from([(
+            "piston_door".to_string(),
+            TestDetails {
+                expect_fail: true,
+                ..TestDetails::default()
+            },
+        )]);
+        let mut out = Vec::new();
+        write_tap(&mut out, &[xfail], &details).unwrap();
+        assert_eq!(
+            read_tap(&String::from_utf8(out).unwrap()),
+            points(&[("piston_door", true)])
+        );
+    }
+
+    #[test]
+    fn comparison_sorts_tests_by_change() {
+        let baseline = points(&[
+            ("steady_pass", true),
+            ("steady_fail", false),
+            ("broke", true),
+            ("fixed", false),
+            ("deleted", true),
+        ]);
+        let current = points(&[
+            ("steady_pass", true),
+            ("steady_fail", false),
+            ("broke", false),
+            ("fixed", true),
+            ("added_failing", false),
+            ("added_passing", true),
+        ]);
+        let comparison = Comparison::new(&baseline, &current);
+        assert_eq!(comparison.regressions, ["broke"]);
+        assert_eq!(comparison.new_failures, ["added_failing"]);
+        assert_eq!(comparison.fixes, ["fixed"]);
+        assert_eq!(comparison.removed, ["deleted"]);
+        // Both steady tests, plus a new test that passes
+        assert_eq!(comparison.unchanged, 3);
+    }
+
+    #[test]
+    fn write_assertions_lists_every_test() {
+        let failed = TestResult::new("lamp".to_string()).with_failure_reason("off".to_string());
+        let mut passed = TestResult::new("never_ran".to_string());
+        passed.success = true;
+        let results = vec![failed, passed];
+        let assertions = HashMap::from([(
+            "lamp".to_string(),
+            vec![AssertionResult {
+                test_name: "lamp".to_string(),
+                tick: 3,
+                pos: Some([1, 0, 2]),
+                expected: "minecraft:redstone_lamp[lit=true]".to_string(),
+                actual: Some("minecraft:redstone_lamp[lit=false]".to_string()),
+                passed: false,
+            }],
+        )]);
+        let mut out = Vec::new();
+        write_assertions(&mut out, &results, &assertions).unwrap();
+        assert!(!results[0].success);
+        let written: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            written,
+            serde_json::json!([
+                {
+                    "test_name": "lamp",
+                    "success": false,
+                    "assertions": [{
+                        "test_name": "lamp",
+                        "tick": 3,
+                        "pos": [1, 0, 2],
+                        "expected": "minecraft:redstone_lamp[lit=true]",
+                        "actual": "minecraft:redstone_lamp[lit=false]",
+                        "passed": false,
+                    }],
+                },
+                { "test_name": "never_ran", "success": true, "assertions": [] },
+            ])
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use crate::config::{self, Config};
+use anyhow::{Context, Result};
+use flint_core::test_spec::TestSpec;
+use std::path::{Path, PathBuf};
+
+/// File name used by `flintmc init` when given a directory
+pub const DEFAULT_SPEC_FILE: &str = "example_test.json";
+
+/// Example spec written by `flintmc init`; `{name}` is replaced with the file stem
+/// JSON has no comments, so the explanation lives in `description`.
+const SPEC_TEMPLATE: &str = r#"{
+  "flintVersion": "0.1",
+  "name": {name},
+  "description": "Places a block and checks it. Positions are relative to the test's area; everything inside setup.cleanup.region is filled with air before and after the test. 'at' is the game tick an entry runs on.",
+  "tags": ["example"],
+  "setup": {
+    "cleanup": {
+      "region": [[-2, 0, -2], [2, 4, 2]]
+    }
+  },
+  "timeline": [
+    {
+      "at": 0,
+      "do": "place",
+      "pos": [0, 1, 0],
+      "block": "minecraft:stone"
+    },
+    {
+      "at": 1,
+      "do": "assert",
+      "checks": [
+        {"pos": [0, 1, 0], "is": "minecraft:stone"}
+      ]
+    }
+  ]
+}
+"#;
+
+/// Starter `flint.toml`; every setting but `server` is commented out at its default
+const CONFIG_TEMPLATE: &str = r#"# flintmc settings; command-line flags override this file
+server = "localhost:25565"
+
+# Offline-mode username for the bot (it needs operator permission)
+# username = "FlintMC_TestBot"
+
+# vanilla, paper, fabric, or legacy for servers without /tick
+# server_flavor = "vanilla"
+
+# Minimum wait in ms after advancing ticks before running assertions
+# tick_delay_ms = 200
+
+# Gap in blocks between automatically arranged test areas
+# spacing = 5
+"#;
+
+/// Write the example spec to `path`, or to `example_test.json` inside it if it is a directory
+/// The template is parsed as a `TestSpec` first, so it never drifts from the schema.
+/// An existing file is only replaced with `force`.
+pub fn write_spec(path: &Path, force: bool) -> Result<PathBuf> {
+    let path = if path.is_dir() {
+        path.join(DEFAULT_SPEC_FILE)
+    } else {
+        path.to_path_buf()
+    };
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists (pass --force to overwrite it)",
+            path.display()
+        );
+    }
+
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("example_test");
+    let content = SPEC_TEMPLATE.replace("{name}", &serde_json::to_string(name)?);
+    serde_json::from_str::<TestSpec>(&content)
+        .context("The built-in spec template no longer matches the spec format")?;
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Write the starter `flint.toml` to the working directory
+/// Returns `false` without touching it if one already exists and `force` is not set.
+pub fn write_config(force: bool) -> Result<bool> {
+    let path = Path::new(config::DEFAULT_CONFIG_FILE);
+    if path.exists() && !force {
+        return Ok(false);
+    }
+
+    toml::from_str::<Config>(CONFIG_TEMPLATE)
+        .context("The built-in config template no longer matches the config format")?;
+    std::fs::write(path, CONFIG_TEMPLATE)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
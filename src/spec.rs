@@ -0,0 +1,1281 @@
+use crate::blocks::validate_block;
+use anyhow::{Context, Result};
+use flint_core::test_spec::{ActionType, TestSpec, TimelineEntry};
+use flint_core::timeline::TimelineAggregate;
+use serde::Deserialize;
+use serde_json::Value;
+use serde_json::value::RawValue;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Action types handled by flintcli on top of the flint-core `ActionType`s
+/// Spec files mix both kinds freely in `timeline`; the loader routes entries
+/// whose `do` names one of these variants to flintcli and leaves the rest to flint-core.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "do", rename_all = "snake_case")]
+pub enum ExtAction {
+    /// Compare every block in a region against an expected structure
+    AssertRegion {
+        region: [[i32; 3]; 2],
+        #[serde(default)]
+        expect: Vec<Placement>,
+        /// Skip positions not listed in `expect` instead of asserting air
+        #[serde(default)]
+        ignore_unspecified: bool,
+    },
+    /// Check that every block in a region is air, e.g. that cleanup really cleared it
+    AssertEmpty { region: [[i32; 3]; 2] },
+    /// Check the redstone power level of a block with a `power` property
+    AssertPower { pos: [i32; 3], strength: u8 },
+    /// Check power dropping by one per block along `length` blocks from `start`, as
+    /// redstone wire does, starting at `from_strength` and stopping at 0
+    AssertPowerLine {
+        start: [i32; 3],
+        direction: String,
+        length: u32,
+        from_strength: u8,
+    },
+    /// Print an annotation; no world interaction and no effect on results
+    Log { message: String },
+    /// Check a block at the first of two ticks in `at`, then that it became `to` at the second
+    AssertChanged {
+        pos: [i32; 3],
+        from: String,
+        to: String,
+    },
+    /// Copy a region (e.g. a pre-built template) so its minimum corner lands on `to`
+    Clone { from: [[i32; 3]; 2], to: [i32; 3] },
+    /// An `assert` whose checks list several acceptable blocks or ignore some states,
+    /// which flint-core cannot parse
+    /// Not in `NAMES`: only `assert` entries with a list in some `is` or an `ignore`
+    /// are routed here.
+    #[serde(rename = "assert")]
+    AssertAny { checks: Vec<AnyCheck> },
+    /// Send a raw server command; coordinates are absolute except in `{pos x y z}` placeholders
+    Command { raw: String },
+    /// A `fill` with a vanilla `/fill` mode or replace filter, which flint-core cannot parse
+    /// Not in `NAMES`: only `fill` entries with `mode` or `replace` are routed here.
+    #[serde(rename = "fill")]
+    FillWith {
+        region: [[i32; 3]; 2],
+        with: String,
+        #[serde(default)]
+        mode: FillMode,
+        /// Only replace blocks matching this block or `#tag`
+        replace: Option<String>,
+    },
+    /// An `assert_state` accepting any integer value of a property within `range`, both ends
+    /// included, e.g. an analog comparator output
+    /// Not in `NAMES`: only `assert_state` entries with `range` are routed here.
+    #[serde(rename = "assert_state")]
+    AssertStateRange {
+        pos: [i32; 3],
+        state: String,
+        range: [i64; 2],
+    },
+    /// Check the raw brightness at a position, as the `light` location predicate tests it:
+    /// the higher of block light and sky light minus the current sky darkening
+    AssertLight { pos: [i32; 3], level: u8 },
+    /// Check a block's `facing` state, e.g. the output side of a repeater or observer
+    AssertFacing { pos: [i32; 3], facing: String },
+    /// Check that every block in the column at `pos` from `from_y` to `to_y` is `is`
+    /// The y of `pos` is not used.
+    AssertColumn {
+        pos: [i32; 3],
+        from_y: i32,
+        to_y: i32,
+        is: String,
+    },
+    /// Check the fluid in a block (`water`, `lava` or `empty`) and optionally its `level`
+    /// Waterlogged blocks count as a water source.
+    AssertFluid {
+        pos: [i32; 3],
+        fluid: String,
+        #[serde(default)]
+        level: Option<u8>,
+    },
+    /// Check that no block in a region changes from the end of the tick in `at` through
+    /// `over_ticks` ticks later; `at` is expanded to every tick of the span when loaded
+    AssertStable {
+        region: [[i32; 3]; 2],
+        over_ticks: u32,
+    },
+    /// Check the text on the front of a sign line by line; `null` skips a line
+    AssertSign {
+        pos: [i32; 3],
+        lines: Vec<Option<String>>,
+    },
+    /// Check how many items a container holds, optionally counting only one item id
+    AssertContainer {
+        pos: [i32; 3],
+        #[serde(default)]
+        item: Option<String>,
+        count: u32,
+    },
+}
+
+impl ExtAction {
+    /// Spec-local positions this action reads or writes (region corners for regions)
+    pub fn positions(&self) -> Vec<[i32; 3]> {
+        match self {
+            ExtAction::AssertRegion { region, .. } => region.to_vec(),
+            ExtAction::AssertEmpty { region } => region.to_vec(),
+            ExtAction::AssertStable { region, .. } => region.to_vec(),
+            ExtAction::AssertPower { pos, .. } => vec![*pos],
+            ExtAction::AssertPowerLine {
+                start,
+                direction,
+                length,
+                ..
+            } => {
+                let step = direction_step(direction).unwrap_or([0, 0, 0]);
+                let last = length.saturating_sub(1) as i32;
+                vec![
+                    *start,
+                    [0, 1, 2].map(|axis| start[axis] + step[axis] * last),
+                ]
+            }
+            ExtAction::Log { .. } => Vec::new(),
+            ExtAction::AssertChanged { pos, .. } => vec![*pos],
+            ExtAction::AssertAny { checks } => checks.iter().map(|c| c.pos).collect(),
+            ExtAction::Command { raw } => command_placeholders(raw)
+                .map(|placeholders| placeholders.into_iter().map(|(_, pos)| pos).collect())
+                .unwrap_or_default(),
+            ExtAction::AssertContainer { pos, .. } => vec![*pos],
+            ExtAction::AssertSign { pos, .. } => vec![*pos],
+            ExtAction::AssertLight { pos, .. } => vec![*pos],
+            ExtAction::AssertFacing { pos, .. } => vec![*pos],
+            ExtAction::AssertStateRange { pos, .. } => vec![*pos],
+            ExtAction::AssertFluid { pos, .. } => vec![*pos],
+            ExtAction::AssertColumn {
+                pos, from_y, to_y, ..
+            } => vec![[pos[0], *from_y, pos[2]], [pos[0], *to_y, pos[2]]],
+            ExtAction::FillWith { region, .. } => region.to_vec(),
+            ExtAction::Clone { from, to } => {
+                let size = [0, 1, 2].map(|axis| (from[1][axis] - from[0][axis]).abs());
+                vec![
+                    from[0],
+                    from[1],
+                    *to,
+                    [0, 1, 2].map(|axis| to[axis] + size[axis]),
+                ]
+            }
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self {
+            ExtAction::Command { .. } | ExtAction::FillWith { .. } => Phase::Mutate,
+            ExtAction::Clone { .. } => Phase::Clone,
+            ExtAction::AssertRegion { .. }
+            | ExtAction::AssertEmpty { .. }
+            | ExtAction::AssertStable { .. }
+            | ExtAction::AssertPower { .. }
+            | ExtAction::AssertPowerLine { .. }
+            | ExtAction::Log { .. }
+            | ExtAction::AssertChanged { .. }
+            | ExtAction::AssertAny { .. }
+            | ExtAction::AssertContainer { .. }
+            | ExtAction::AssertSign { .. }
+            | ExtAction::AssertLight { .. }
+            | ExtAction::AssertFacing { .. }
+            | ExtAction::AssertStateRange { .. }
+            | ExtAction::AssertFluid { .. }
+            | ExtAction::AssertColumn { .. } => Phase::Check,
+        }
+    }
+
+    /// One-line description for failure context, e.g. `clone [0, 1, 0] to [2, 1, 2] -> [5, 1, 0]`
+    pub fn summary(&self) -> String {
+        match self {
+            ExtAction::AssertRegion { region, .. } => format!(
+                "assert_region {} to {}",
+                fmt_pos(region[0]),
+                fmt_pos(region[1])
+            ),
+            ExtAction::AssertEmpty { region } => format!(
+                "assert_empty {} to {}",
+                fmt_pos(region[0]),
+                fmt_pos(region[1])
+            ),
+            ExtAction::AssertPower { pos, strength } => {
+                format!("assert_power {} = {}", fmt_pos(*pos), strength)
+            }
+            ExtAction::AssertPowerLine {
+                start,
+                direction,
+                length,
+                from_strength,
+            } => format!(
+                "assert_power_line {} {} x{} from {}",
+                fmt_pos(*start),
+                direction,
+                length,
+                from_strength
+            ),
+            ExtAction::Log { message } => format!("log \"{}\"", message),
+            ExtAction::AssertChanged { pos, from, to } => {
+                format!("assert_changed {} {} -> {}", fmt_pos(*pos), from, to)
+            }
+            ExtAction::AssertStable { region, over_ticks } => format!(
+                "assert_stable {} to {} over {} ticks",
+                fmt_pos(region[0]),
+                fmt_pos(region[1]),
+                over_ticks
+            ),
+            ExtAction::Clone { from, to } => format!(
+                "clone {} to {} -> {}",
+                fmt_pos(from[0]),
+                fmt_pos(from[1]),
+                fmt_pos(*to)
+            ),
+            ExtAction::AssertAny { checks } => format!(
+                "assert {}",
+                checks
+                    .iter()
+                    .map(|c| format!("{} is {}", fmt_pos(c.pos), c.is.as_slice().join(" | ")))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ExtAction::Command { raw } => format!("command /{}", raw.trim_start_matches('/')),
+            ExtAction::FillWith {
+                region, with, mode, ..
+            } => format!(
+                "fill {} to {} = {} ({:?})",
+                fmt_pos(region[0]),
+                fmt_pos(region[1]),
+                with,
+                mode
+            ),
+            ExtAction::AssertContainer { pos, item, count } => format!(
+                "assert_container {} holds {} {}",
+                fmt_pos(*pos),
+                count,
+                item.as_deref().unwrap_or("items")
+            ),
+            ExtAction::AssertSign { pos, lines } => format!(
+                "assert_sign {} = {}",
+                fmt_pos(*pos),
+                lines
+                    .iter()
+                    .map(|line| line.as_deref().unwrap_or("*"))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            ),
+            ExtAction::AssertLight { pos, level } => {
+                format!("assert_light {} = {}", fmt_pos(*pos), level)
+            }
+            ExtAction::AssertFacing { pos, facing } => {
+                format!("assert_facing {} = {}", fmt_pos(*pos), facing)
+            }
+            ExtAction::AssertStateRange { pos, state, range } => format!(
+                "assert_state {} {} in {}..={}",
+                fmt_pos(*pos),
+                state,
+                range[0],
+                range[1]
+            ),
+            ExtAction::AssertFluid { pos, fluid, level } => match level {
+                Some(level) => {
+                    format!("assert_fluid {} = {} level {}", fmt_pos(*pos), fluid, level)
+                }
+                None => format!("assert_fluid {} = {}", fmt_pos(*pos), fluid),
+            },
+            ExtAction::AssertColumn {
+                pos,
+                from_y,
+                to_y,
+                is,
+            } => format!(
+                "assert_column [{}, {}..{}, {}] is {}",
+                pos[0], from_y, to_y, pos[2], is
+            ),
+        }
+    }
+
+    /// Names accepted in the `do` field for extension actions
+    const NAMES: &'static [&'static str] = &[
+        "assert_region",
+        "assert_power",
+        "log",
+        "assert_changed",
+        "clone",
+        "command",
+        "assert_container",
+        "assert_light",
+        "assert_facing",
+        "assert_column",
+        "assert_fluid",
+        "assert_empty",
+        "assert_power_line",
+        "assert_sign",
+        "assert_stable",
+    ];
+
+    fn handles(value: &Value) -> bool {
+        value
+            .get("do")
+            .and_then(Value::as_str)
+            .is_some_and(|name| Self::NAMES.contains(&name))
+            || Self::lists_alternatives(value)
+            || Self::sets_fill_mode(value)
+            || Self::sets_state_range(value)
+    }
+
+    /// Whether an `assert_state` entry gives a `range` instead of `values`
+    fn sets_state_range(value: &Value) -> bool {
+        value.get("do").and_then(Value::as_str) == Some("assert_state")
+            && value.get("range").is_some()
+    }
+
+    /// Whether a `fill` entry uses a mode or replace filter
+    fn sets_fill_mode(value: &Value) -> bool {
+        value.get("do").and_then(Value::as_str) == Some("fill")
+            && (value.get("mode").is_some() || value.get("replace").is_some())
+    }
+
+    /// Whether an `assert` entry has a check with a list of acceptable blocks or an `ignore`
+    fn lists_alternatives(value: &Value) -> bool {
+        value.get("do").and_then(Value::as_str) == Some("assert")
+            && value
+                .get("checks")
+                .and_then(Value::as_array)
+                .is_some_and(|checks| {
+                    checks.iter().any(|c| {
+                        c.get("is").is_some_and(Value::is_array) || c.get("ignore").is_some()
+                    })
+                })
+    }
+}
+
+/// Values of the `facing` block state; most horizontal blocks only use the first four
+pub const FACINGS: &[&str] = &["north", "south", "east", "west", "up", "down"];
+
+/// One block's step in a `FACINGS` direction, e.g. `[0, 0, -1]` for north
+pub fn direction_step(direction: &str) -> Option<[i32; 3]> {
+    match direction {
+        "north" => Some([0, 0, -1]),
+        "south" => Some([0, 0, 1]),
+        "east" => Some([1, 0, 0]),
+        "west" => Some([-1, 0, 0]),
+        "up" => Some([0, 1, 0]),
+        "down" => Some([0, -1, 0]),
+        _ => None,
+    }
+}
+
+/// Values of `fluid` in `assert_fluid`
+pub const FLUIDS: &[&str] = &["water", "lava", "empty"];
+
+/// Modifier of a vanilla `/fill`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FillMode {
+    /// Replace every block in the region (plain `/fill`)
+    #[default]
+    Solid,
+    /// Outer layer of `with`, inside filled with air
+    Hollow,
+    /// Outer layer of `with`, inside left untouched
+    Outline,
+    /// Only replace air
+    Keep,
+    /// Replace every block, or only those matching `replace`
+    Replace,
+}
+
+impl FillMode {
+    /// Suffix for the `/fill` command, after the block
+    pub fn command_suffix(self, filter: Option<&str>) -> String {
+        match (self, filter) {
+            (FillMode::Solid | FillMode::Replace, Some(filter)) => format!(" replace {}", filter),
+            (FillMode::Solid, None) => String::new(),
+            (FillMode::Replace, None) => " replace".to_string(),
+            (FillMode::Hollow, _) => " hollow".to_string(),
+            (FillMode::Outline, _) => " outline".to_string(),
+            (FillMode::Keep, _) => " keep".to_string(),
+        }
+    }
+}
+
+/// Block check whose `is` may be a single block or a list of acceptable blocks
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnyCheck {
+    pub pos: [i32; 3],
+    pub is: OneOrMany,
+    /// States not compared even if `is` lists them
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            OneOrMany::One(value) => std::slice::from_ref(value),
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+/// Entry keys only flintcli understands; entries using them bypass flint-core
+const ENTRY_KEYS: &[&str] = &["id", "requires", "within", "repeat"];
+
+/// Upper bound for an entry's `repeat`, so a typo cannot stall the whole run
+pub const MAX_REPEAT: u32 = 256;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Placement {
+    pub pos: [i32; 3],
+    pub block: String,
+}
+
+/// A timeline entry executed by flintcli rather than flint-core
+#[derive(Debug, Clone)]
+pub struct ExtEntry {
+    /// Ticks the entry runs at; multi-tick `assert_state` uses `values[i]` at the i-th tick
+    pub at: Vec<u32>,
+    /// Name under which this entry's assertion outcome is recorded
+    pub id: Option<String>,
+    /// Only run when the assertion with this id passed earlier in the same test
+    pub requires: Option<String>,
+    /// Extra ticks a failing assertion is re-checked before it counts as failed
+    pub within: u32,
+    /// How often the action runs within its tick; assertions must hold on every read
+    pub repeat: u32,
+    pub action: EntryAction,
+    /// Where the entry is written, when it was loaded from a file
+    pub source: Option<SourceLocation>,
+}
+
+#[derive(Debug, Clone)]
+pub enum EntryAction {
+    /// A flint-core action that uses flintcli-only entry keys
+    Core(ActionType),
+    Ext(ExtAction),
+}
+
+impl EntryAction {
+    pub fn positions(&self) -> Vec<[i32; 3]> {
+        match self {
+            EntryAction::Core(action) => action_positions(action),
+            EntryAction::Ext(action) => action.positions(),
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self {
+            EntryAction::Core(action) => action_phase(action),
+            EntryAction::Ext(action) => action.phase(),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            EntryAction::Core(action) => action_summary(action),
+            EntryAction::Ext(action) => action.summary(),
+        }
+    }
+}
+
+impl ExtEntry {
+    /// Whether a raw timeline entry must be handled by flintcli
+    fn routed_to_cli(value: &Value) -> bool {
+        ExtAction::handles(value) || ENTRY_KEYS.iter().any(|key| value.get(key).is_some())
+    }
+
+    pub(crate) fn from_value(mut value: Value) -> Result<Self> {
+        let Some(obj) = value.as_object_mut() else {
+            anyhow::bail!("Timeline entry must be an object");
+        };
+        let mut at: Vec<u32> = match obj.remove("at") {
+            Some(ticks @ Value::Array(_)) => serde_json::from_value(ticks)?,
+            Some(tick) => vec![serde_json::from_value(tick)?],
+            None => anyhow::bail!("Timeline entry is missing 'at'"),
+        };
+        let id = obj.remove("id").map(serde_json::from_value).transpose()?;
+        let requires = obj
+            .remove("requires")
+            .map(serde_json::from_value)
+            .transpose()?;
+        let within = obj
+            .remove("within")
+            .map(serde_json::from_value)
+            .transpose()?
+            .unwrap_or(0);
+        let requested_repeat: u32 = obj
+            .remove("repeat")
+            .map(serde_json::from_value)
+            .transpose()?
+            .unwrap_or(1);
+        let repeat = requested_repeat.clamp(1, MAX_REPEAT);
+        if repeat != requested_repeat {
+            tracing::warn!(
+                "repeat {} clamped to {} (allowed range is 1..={})",
+                requested_repeat,
+                repeat,
+                MAX_REPEAT
+            );
+        }
+
+        let action = if ExtAction::handles(&value) {
+            if value.get("do").and_then(Value::as_str) == Some("assert_light")
+                && (value.get("block_light").is_some() || value.get("sky_light").is_some())
+            {
+                anyhow::bail!(
+                    "assert_light can't check block_light or sky_light separately; give the combined brightness as 'level'"
+                );
+            }
+            if ExtAction::sets_state_range(&value) && value.get("values").is_some() {
+                anyhow::bail!("assert_state takes either 'values' or 'range', not both");
+            }
+            let action: ExtAction = serde_json::from_value(value)?;
+            if matches!(action, ExtAction::AssertChanged { .. }) && at.len() != 2 {
+                anyhow::bail!("assert_changed needs exactly two ticks in 'at', e.g. [3, 5]");
+            }
+            // The region is captured on the first tick and compared on every later one
+            if let ExtAction::AssertStable { over_ticks, .. } = &action {
+                let [start] = at[..] else {
+                    anyhow::bail!("assert_stable needs a single tick in 'at'");
+                };
+                if *over_ticks == 0 {
+                    anyhow::bail!("assert_stable needs over_ticks of at least 1");
+                }
+                at = (start..=start.saturating_add(*over_ticks)).collect();
+            }
+            if let ExtAction::AssertFacing { facing, .. } = &action
+                && !FACINGS.contains(&facing.as_str())
+            {
+                anyhow::bail!(
+                    "Unknown facing '{}' in assert_facing (expected one of {})",
+                    facing,
+                    FACINGS.join(", ")
+                );
+            }
+            if let ExtAction::AssertPowerLine {
+                direction,
+                length,
+                from_strength,
+                ..
+            } = &action
+            {
+                if direction_step(direction).is_none() {
+                    anyhow::bail!(
+                        "Unknown direction '{}' in assert_power_line (expected one of {})",
+                        direction,
+                        FACINGS.join(", ")
+                    );
+                }
+                if *length == 0 || *from_strength > 15 {
+                    anyhow::bail!(
+                        "assert_power_line needs a length of at least 1 and from_strength 0-15"
+                    );
+                }
+            }
+            if let ExtAction::AssertStateRange { range, .. } = &action
+                && range[0] > range[1]
+            {
+                anyhow::bail!(
+                    "assert_state range [{}, {}] is empty; give it as [min, max]",
+                    range[0],
+                    range[1]
+                );
+            }
+            if let ExtAction::AssertSign { lines, .. } = &action
+                && lines.len() > 4
+            {
+                anyhow::bail!("assert_sign lists {} lines; a sign has 4", lines.len());
+            }
+            if let ExtAction::AssertFluid { fluid, level, .. } = &action {
+                if !FLUIDS.contains(&fluid.trim_start_matches("minecraft:")) {
+                    anyhow::bail!(
+                        "Unknown fluid '{}' in assert_fluid (expected one of {})",
+                        fluid,
+                        FLUIDS.join(", ")
+                    );
+                }
+                if level.is_some_and(|level| level > 15) {
+                    anyhow::bail!("assert_fluid level must be 0-15 (0 is a source)");
+                }
+            }
+            if let ExtAction::Command { raw } = &action {
+                command_placeholders(raw)?;
+            }
+            if let ExtAction::FillWith {
+                mode,
+                replace: Some(_),
+                ..
+            } = &action
+                && !matches!(mode, FillMode::Solid | FillMode::Replace)
+            {
+                anyhow::bail!("fill 'replace' filter only works with mode 'replace'");
+            }
+            EntryAction::Ext(action)
+        } else {
+            EntryAction::Core(serde_json::from_value(value)?)
+        };
+
+        Ok(Self {
+            at,
+            id,
+            requires,
+            within,
+            repeat,
+            action,
+            source: None,
+        })
+    }
+}
+
+/// `{pos x y z}` placeholders in a raw command, as byte ranges and spec-local positions
+pub fn command_placeholders(raw: &str) -> Result<Vec<(Range<usize>, [i32; 3])>> {
+    let mut placeholders = Vec::new();
+    let mut searched = 0;
+    while let Some(start) = raw[searched..].find("{pos ").map(|idx| idx + searched) {
+        let Some(end) = raw[start..].find('}').map(|idx| idx + start + 1) else {
+            anyhow::bail!("Unclosed placeholder in command '{}'", raw);
+        };
+        let placeholder = &raw[start..end];
+        let coords: Option<Vec<i32>> = placeholder["{pos ".len()..placeholder.len() - 1]
+            .split_whitespace()
+            .map(|coord| coord.parse().ok())
+            .collect();
+        let Some(Ok(pos)) = coords.map(<[i32; 3]>::try_from) else {
+            anyhow::bail!(
+                "Placeholder '{}' in command '{}' must be {{pos x y z}} with integer coordinates",
+                placeholder,
+                raw
+            );
+        };
+        placeholders.push((start..end, pos));
+        searched = end;
+    }
+    Ok(placeholders)
+}
+
+/// Replace the `{pos x y z}` placeholders of a raw command with world coordinates
+pub fn resolve_command(raw: &str, offset: [i32; 3]) -> Result<String> {
+    let mut command = String::new();
+    let mut copied = 0;
+    for (range, pos) in command_placeholders(raw)? {
+        command.push_str(&raw[copied..range.start]);
+        command.push_str(&format!(
+            "{} {} {}",
+            pos[0] + offset[0],
+            pos[1] + offset[1],
+            pos[2] + offset[2]
+        ));
+        copied = range.end;
+    }
+    command.push_str(&raw[copied..]);
+    Ok(command.trim_start_matches('/').to_string())
+}
+
+/// File and line a timeline entry is written at, for editor-friendly failure locations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    /// 1-based line of the entry's opening brace
+    pub line: usize,
+}
+
+/// When an action runs relative to the other actions of the same tick
+/// All of a tick's mutations run first, then its clones, then its checks, whatever
+/// test they belong to and wherever they appear in the spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Mutate,
+    Clone,
+    Check,
+}
+
+pub fn action_phase(action: &ActionType) -> Phase {
+    match action {
+        ActionType::Place { .. }
+        | ActionType::PlaceEach { .. }
+        | ActionType::Fill { .. }
+        | ActionType::Remove { .. } => Phase::Mutate,
+        ActionType::Assert { .. } | ActionType::AssertState { .. } => Phase::Check,
+    }
+}
+
+/// One-line description of a flint-core action for failure context
+pub fn action_summary(action: &ActionType) -> String {
+    match action {
+        ActionType::Place { pos, block } => format!("place {} = {}", fmt_pos(*pos), block),
+        ActionType::PlaceEach { blocks } => format!(
+            "place_each {}",
+            blocks
+                .iter()
+                .map(|p| format!("{} = {}", fmt_pos(p.pos), p.block))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        ActionType::Fill { region, with } => format!(
+            "fill {} to {} = {}",
+            fmt_pos(region[0]),
+            fmt_pos(region[1]),
+            with
+        ),
+        ActionType::Remove { pos } => format!("remove {}", fmt_pos(*pos)),
+        ActionType::Assert { checks } => format!(
+            "assert {}",
+            checks
+                .iter()
+                .map(|c| format!("{} is {}", fmt_pos(c.pos), c.is))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        ActionType::AssertState { pos, state, .. } => {
+            format!("assert_state {} {}", fmt_pos(*pos), state)
+        }
+    }
+}
+
+fn fmt_pos(pos: [i32; 3]) -> String {
+    format!("[{}, {}, {}]", pos[0], pos[1], pos[2])
+}
+
+/// Spec-local positions a flint-core action reads or writes (region corners for fills)
+pub fn action_positions(action: &ActionType) -> Vec<[i32; 3]> {
+    match action {
+        ActionType::Place { pos, .. } => vec![*pos],
+        ActionType::PlaceEach { blocks } => blocks.iter().map(|p| p.pos).collect(),
+        ActionType::Fill { region, .. } => region.to_vec(),
+        ActionType::Remove { pos } => vec![*pos],
+        ActionType::Assert { checks } => checks.iter().map(|c| c.pos).collect(),
+        ActionType::AssertState { pos, .. } => vec![*pos],
+    }
+}
+
+/// Blocks a test area is filled with before and after the test
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CleanupFill {
+    /// Block for the whole cleanup region
+    pub block: String,
+    /// Block for the bottom layer of each cleanup region, filled after `block`
+    pub floor: Option<String>,
+}
+
+impl Default for CleanupFill {
+    fn default() -> Self {
+        Self {
+            block: "air".to_string(),
+            floor: None,
+        }
+    }
+}
+
+/// flintcli-specific data read from a spec file alongside its `TestSpec`
+#[derive(Debug, Clone, Default)]
+pub struct SpecExtras {
+    pub actions: Vec<ExtEntry>,
+    /// Disjoint regions from `setup.cleanup.regions`, each cleaned separately
+    pub cleanup_regions: Vec<[[i32; 3]; 2]>,
+    /// World position the spec's coordinates are relative to, for specs written in
+    /// absolute coordinates; subtracted from every position before the test's offset
+    pub origin: [i32; 3],
+    /// Source of every entry left in the `TestSpec` timeline, by index
+    pub core_sources: Vec<Option<SourceLocation>>,
+    /// Shifts of the copies to run from `instances`; empty runs the spec once, unshifted
+    pub instances: Vec<[i32; 3]>,
+    /// Share of assertions that must pass for the test to pass; all of them if unset
+    pub min_pass_ratio: Option<f32>,
+    /// The spec's `description`, carried into reports
+    pub description: Option<String>,
+    /// Free-form `metadata` such as an owner or ticket id, carried into reports
+    pub metadata: BTreeMap<String, String>,
+    /// What the cleanup regions are filled with, from `cleanup_block` and `cleanup_floor`
+    pub cleanup_fill: CleanupFill,
+    /// Known-broken test: passes when its checks fail, and fails as an unexpected pass
+    pub expect_fail: bool,
+    /// Tick to halt the run at for inspection, overriding `--stop-at-tick`
+    pub stop_at_tick: Option<u32>,
+    /// Server the test must run on instead of the default `--server`
+    pub server: Option<String>,
+}
+
+impl SpecExtras {
+    /// Regions to fill with the cleanup block before and after the test
+    /// Falls back to the spec's single bounding box when no regions were declared.
+    pub fn cleanup_regions(&self, spec: &TestSpec) -> Vec<[[i32; 3]; 2]> {
+        if self.cleanup_regions.is_empty() {
+            vec![spec.cleanup_region()]
+        } else {
+            self.cleanup_regions.clone()
+        }
+    }
+
+    /// Source of a flint-core entry, found by identity in the spec's timeline
+    pub fn core_source(&self, spec: &TestSpec, entry: &TimelineEntry) -> Option<&SourceLocation> {
+        let idx = spec.timeline.iter().position(|e| std::ptr::eq(e, entry))?;
+        self.core_sources.get(idx)?.as_ref()
+    }
+
+    /// Last tick with a flintcli-handled entry
+    pub fn max_tick(&self) -> u32 {
+        self.actions
+            .iter()
+            .flat_map(|e| e.at.iter().map(move |tick| tick + e.within))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Load a spec file, splitting out extension actions before flint-core parses the rest
+pub fn load_spec(path: &Path) -> Result<(TestSpec, SpecExtras)> {
+    let (mut value, sources) = load_with_includes(path, &mut Vec::new())?;
+
+    if let Some(markers) = value.as_object_mut().and_then(|obj| obj.remove("markers")) {
+        let markers: HashMap<String, [i32; 3]> = serde_json::from_value(markers)
+            .with_context(|| format!("Invalid markers in {}", path.display()))?;
+        resolve_markers(&mut value, &markers)
+            .with_context(|| format!("Invalid marker position in {}", path.display()))?;
+    }
+    // After markers, so `from` can itself be relative to a marker
+    if let Some(Value::Array(timeline)) = value.get_mut("timeline") {
+        for entry in timeline {
+            resolve_relative(entry)
+                .with_context(|| format!("Invalid relative position in {}", path.display()))?;
+        }
+    }
+
+    let mut extras = SpecExtras::default();
+    if let Some(Value::Array(timeline)) = value.get_mut("timeline") {
+        let sources = sources.into_iter().map(Some).chain(std::iter::repeat(None));
+        let (ext, core): (Vec<_>, Vec<_>) = timeline
+            .drain(..)
+            .zip(sources)
+            .partition(|(entry, _)| ExtEntry::routed_to_cli(entry));
+        (*timeline, extras.core_sources) = core.into_iter().unzip();
+        for (entry, source) in ext {
+            let mut entry = ExtEntry::from_value(entry)
+                .with_context(|| format!("Invalid timeline entry in {}", path.display()))?;
+            entry.source = source;
+            extras.actions.push(entry);
+        }
+    }
+
+    if let Some(instances) = value
+        .as_object_mut()
+        .and_then(|obj| obj.remove("instances"))
+    {
+        extras.instances = serde_json::from_value(instances)
+            .with_context(|| format!("Invalid instances in {}", path.display()))?;
+    }
+
+    extras.description = value
+        .get("description")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    if let Some(metadata) = value.as_object_mut().and_then(|obj| obj.remove("metadata")) {
+        extras.metadata = serde_json::from_value(metadata).with_context(|| {
+            format!(
+                "Invalid metadata in {} (expected string values)",
+                path.display()
+            )
+        })?;
+    }
+
+    if let Some(server) = value.as_object_mut().and_then(|obj| obj.remove("server")) {
+        extras.server = serde_json::from_value(server)
+            .with_context(|| format!("Invalid server in {}", path.display()))?;
+    }
+
+    if let Some(stop) = value
+        .as_object_mut()
+        .and_then(|obj| obj.remove("stop_at_tick"))
+    {
+        extras.stop_at_tick = serde_json::from_value(stop)
+            .with_context(|| format!("Invalid stop_at_tick in {}", path.display()))?;
+    }
+
+    if let Some(expect_fail) = value
+        .as_object_mut()
+        .and_then(|obj| obj.remove("expect_fail"))
+    {
+        extras.expect_fail = serde_json::from_value(expect_fail)
+            .with_context(|| format!("Invalid expect_fail in {}", path.display()))?;
+    }
+
+    if let Some(ratio) = value
+        .as_object_mut()
+        .and_then(|obj| obj.remove("min_pass_ratio"))
+    {
+        let ratio: f32 = serde_json::from_value(ratio)
+            .with_context(|| format!("Invalid min_pass_ratio in {}", path.display()))?;
+        if !(0.0..=1.0).contains(&ratio) {
+            anyhow::bail!(
+                "min_pass_ratio must be between 0 and 1, got {} in {}",
+                ratio,
+                path.display()
+            );
+        }
+        extras.min_pass_ratio = Some(ratio);
+    }
+
+    if let Some(block) = value
+        .as_object_mut()
+        .and_then(|obj| obj.remove("cleanup_block"))
+    {
+        extras.cleanup_fill.block = serde_json::from_value(block)
+            .with_context(|| format!("Invalid cleanup_block in {}", path.display()))?;
+    }
+    if let Some(floor) = value
+        .as_object_mut()
+        .and_then(|obj| obj.remove("cleanup_floor"))
+    {
+        extras.cleanup_fill.floor = serde_json::from_value(floor)
+            .with_context(|| format!("Invalid cleanup_floor in {}", path.display()))?;
+    }
+    for block in std::iter::once(&extras.cleanup_fill.block).chain(&extras.cleanup_fill.floor) {
+        validate_block(block)
+            .with_context(|| format!("Invalid cleanup block in {}", path.display()))?;
+    }
+
+    if let Some(origin) = value.as_object_mut().and_then(|obj| obj.remove("origin")) {
+        extras.origin = serde_json::from_value(origin)
+            .with_context(|| format!("Invalid origin in {}", path.display()))?;
+    }
+
+    // flint-core only knows a single `region`; give it the bounding box of all regions
+    if let Some(cleanup) = value
+        .pointer_mut("/setup/cleanup")
+        .and_then(Value::as_object_mut)
+        && let Some(regions) = cleanup.remove("regions")
+    {
+        extras.cleanup_regions = serde_json::from_value(regions)
+            .with_context(|| format!("Invalid cleanup regions in {}", path.display()))?;
+        if !cleanup.contains_key("region")
+            && let Some(bounds) = bounding_box(&extras.cleanup_regions)
+        {
+            cleanup.insert("region".to_string(), serde_json::to_value(bounds)?);
+        }
+    }
+
+    let spec: TestSpec = serde_json::from_value(value)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok((spec, extras))
+}
+
+/// Overview of a spec for `flintmc list`
+#[derive(Debug)]
+pub struct SpecInfo {
+    pub name: String,
+    pub description: Option<String>,
+    /// Last tick of the timeline, flintcli entries included
+    pub max_tick: u32,
+    /// Blocks in the cleanup regions, filled before and after the test
+    pub cleanup_volume: i64,
+}
+
+/// Load a spec file and summarise it without connecting to a server
+pub fn spec_info(path: &Path) -> Result<SpecInfo> {
+    let (spec, extras) = load_spec(path)?;
+    let cleanup_volume = extras
+        .cleanup_regions(&spec)
+        .iter()
+        .map(|region| {
+            (0..3)
+                .map(|axis| i64::from((region[1][axis] - region[0][axis]).abs()) + 1)
+                .product::<i64>()
+        })
+        .sum();
+    let name = spec.name.clone();
+    let aggregate = TimelineAggregate::from_tests(&[(spec, [0, 0, 0])]);
+
+    Ok(SpecInfo {
+        name,
+        description: extras.description,
+        max_tick: aggregate.max_tick.max(extras.max_tick()),
+        cleanup_volume,
+    })
+}
+
+/// Read a spec file as JSON with its includes merged, without interpreting it
+pub(crate) fn load_json(path: &Path) -> Result<Value> {
+    Ok(load_with_includes(path, &mut Vec::new())?.0)
+}
+
+/// Tags of a spec file, includes merged; a spec without `tags` has none
+pub fn load_tags(path: &Path) -> Result<Vec<String>> {
+    match load_json(path)?.get("tags") {
+        Some(tags) => serde_json::from_value(tags.clone())
+            .with_context(|| format!("Invalid tags in {}", path.display())),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Server a spec file pins its test to, includes merged; `None` runs on the default server
+pub fn load_server(path: &Path) -> Result<Option<String>> {
+    match load_json(path)?.get("server") {
+        Some(server) => serde_json::from_value(server.clone())
+            .with_context(|| format!("Invalid server in {}", path.display())),
+        None => Ok(None),
+    }
+}
+
+/// 1-based line of every top-level timeline entry in a spec file's JSON
+fn timeline_lines(content: &str) -> Vec<usize> {
+    #[derive(Deserialize)]
+    struct Timeline<'a> {
+        #[serde(borrow, default)]
+        timeline: Vec<&'a RawValue>,
+    }
+
+    let Ok(parsed) = serde_json::from_str::<Timeline>(content) else {
+        return Vec::new();
+    };
+    parsed
+        .timeline
+        .iter()
+        .map(|entry| {
+            let offset = entry.get().as_ptr() as usize - content.as_ptr() as usize;
+            content[..offset].matches('\n').count() + 1
+        })
+        .collect()
+}
+
+/// Replace every `{"relative_to": marker, "offset": [x, y, z]}` position with the
+/// marker's position plus the offset, before any test offset is applied
+fn resolve_markers(value: &mut Value, markers: &HashMap<String, [i32; 3]>) -> Result<()> {
+    match value {
+        Value::Object(obj) if obj.contains_key("relative_to") => {
+            let Some(name) = obj.get("relative_to").and_then(Value::as_str) else {
+                anyhow::bail!("'relative_to' must be a marker name");
+            };
+            let Some(marker) = markers.get(name) else {
+                anyhow::bail!("Unknown marker '{}'", name);
+            };
+            let offset: [i32; 3] = match obj.get("offset") {
+                Some(offset) => serde_json::from_value(offset.clone())
+                    .with_context(|| format!("Invalid offset from marker '{}'", name))?,
+                None => [0, 0, 0],
+            };
+            let pos = [0, 1, 2].map(|axis| marker[axis] + offset[axis]);
+            *value = serde_json::to_value(pos)?;
+        }
+        Value::Object(obj) => {
+            for item in obj.values_mut() {
+                resolve_markers(item, markers)?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                resolve_markers(item, markers)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace every `{"from": [x, y, z], "rel": [dx, dy, dz]}` position in a timeline
+/// entry with `from` moved by `rel`, before any test offset is applied
+pub(crate) fn resolve_relative(value: &mut Value) -> Result<()> {
+    match value {
+        Value::Object(obj) if obj.contains_key("from") && obj.contains_key("rel") => {
+            let from: [i32; 3] = serde_json::from_value(obj["from"].clone())
+                .context("'from' of a relative position must be [x, y, z]")?;
+            let rel: [i32; 3] =
+                serde_json::from_value(obj["rel"].clone()).context("'rel' must be [dx, dy, dz]")?;
+            let pos = [0, 1, 2].map(|axis| from[axis] + rel[axis]);
+            *value = serde_json::to_value(pos)?;
+        }
+        Value::Object(obj) => {
+            for item in obj.values_mut() {
+                resolve_relative(item)?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                resolve_relative(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Smallest region containing every given region
+fn bounding_box(regions: &[[[i32; 3]; 2]]) -> Option<[[i32; 3]; 2]> {
+    regions.iter().fold(None, |bounds, region| {
+        let [mut min, mut max] = bounds.unwrap_or([region[0], region[0]]);
+        for corner in region {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(corner[axis]);
+                max[axis] = max[axis].max(corner[axis]);
+            }
+        }
+        Some([min, max])
+    })
+}
+
+/// Read a spec file as JSON and merge in every file listed in its `include` array
+/// Include paths are relative to the including file. Included timelines run before
+/// the including file's own entries; any other top-level key is only taken from an
+/// include when the including file does not define it. Also returns the source of
+/// every entry in the merged timeline, in order.
+fn load_with_includes(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<(Value, Vec<SourceLocation>)> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        anyhow::bail!("Include cycle detected: {}", chain.join(" -> "));
+    }
+
+    let content = std::fs::read_to_string(&canonical)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    let own_sources: Vec<SourceLocation> = timeline_lines(&content)
+        .into_iter()
+        .map(|line| SourceLocation {
+            file: canonical.clone(),
+            line,
+        })
+        .collect();
+
+    let includes = match value.as_object_mut().and_then(|obj| obj.remove("include")) {
+        Some(Value::Array(includes)) => includes,
+        Some(_) => anyhow::bail!("'include' in {} must be an array of paths", path.display()),
+        None => return Ok((value, own_sources)),
+    };
+
+    stack.push(canonical.clone());
+    let base_dir = canonical.parent().unwrap_or(Path::new("."));
+    let mut included_timeline = Vec::new();
+    let mut sources = Vec::new();
+    for include in includes {
+        let Some(relative) = include.as_str() else {
+            anyhow::bail!("'include' in {} must be an array of paths", path.display());
+        };
+        let (fragment, fragment_sources) = load_with_includes(&base_dir.join(relative), stack)?;
+        sources.extend(fragment_sources);
+        let Value::Object(fragment) = fragment else {
+            anyhow::bail!("Included file {} is not a JSON object", relative);
+        };
+
+        for (key, fragment_value) in fragment {
+            if key == "timeline" {
+                if let Value::Array(entries) = fragment_value {
+                    included_timeline.extend(entries);
+                }
+            } else if let Some(obj) = value.as_object_mut() {
+                obj.entry(key).or_insert(fragment_value);
+            }
+        }
+    }
+    stack.pop();
+
+    if let Some(obj) = value.as_object_mut() {
+        let own_timeline = match obj.remove("timeline") {
+            Some(Value::Array(entries)) => entries,
+            _ => Vec::new(),
+        };
+        included_timeline.extend(own_timeline);
+        obj.insert("timeline".to_string(), Value::Array(included_timeline));
+    }
+    sources.extend(own_sources);
+
+    Ok((value, sources))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a spec to a file of its own under the temp directory
+    fn spec_file(name: &str, spec: Value) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("flintcli-spec-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.json", name));
+        std::fs::write(&path, spec.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn disjoint_cleanup_regions_are_cleaned_separately() {
+        let near = [[0, 0, 0], [2, 2, 2]];
+        let far = [[40, 0, 40], [42, 2, 42]];
+        let path = spec_file(
+            "two_regions",
+            serde_json::json!({
+                "flintVersion": "0.1",
+                "name": "two_regions",
+                "setup": { "cleanup": { "regions": [near, far] } },
+                "timeline": [
+                    { "at": 0, "do": "place", "pos": [1, 1, 1], "block": "minecraft:stone" },
+                    { "at": 0, "do": "place", "pos": [41, 1, 41], "block": "minecraft:stone" }
+                ]
+            }),
+        );
+
+        let (spec, extras) = load_spec(&path).unwrap();
+        let regions = extras.cleanup_regions(&spec);
+        assert_eq!(regions, vec![near, far]);
+        assert_eq!(crate::layout::merge_regions(&regions), vec![near, far]);
+    }
+
+    #[test]
+    fn relative_positions_resolve_from_their_base() {
+        let mut entry = serde_json::json!({
+            "at": 2, "do": "assert",
+            "checks": [{ "pos": { "from": [3, 101, 0], "rel": [0, -1, 0] }, "is": "minecraft:stone" }]
+        });
+        resolve_relative(&mut entry).unwrap();
+        assert_eq!(entry["checks"][0]["pos"], serde_json::json!([3, 100, 0]));
+
+        // `from` alone is an action's own field, e.g. the region `clone` copies
+        let mut clone = serde_json::json!({
+            "at": 0, "do": "clone", "from": [[0, 0, 0], [1, 1, 1]], "to": [5, 0, 0]
+        });
+        let unchanged = clone.clone();
+        resolve_relative(&mut clone).unwrap();
+        assert_eq!(clone, unchanged);
+    }
+
+    #[test]
+    fn relative_positions_in_a_spec_file() {
+        let path = spec_file(
+            "relative_metadata",
+            serde_json::json!({
+                "flintVersion": "0.1",
+                "name": "relative_metadata",
+                "metadata": { "rel": "v2", "from": "bisect" },
+                "setup": { "cleanup": { "region": [[0, 0, 0], [4, 4, 4]] } },
+                "markers": { "lamp": [2, 2, 2] },
+                "timeline": [
+                    {
+                        "at": 0, "do": "place",
+                        "pos": { "from": { "relative_to": "lamp" }, "rel": [0, -1, 0] },
+                        "block": "minecraft:redstone_block"
+                    }
+                ]
+            }),
+        );
+
+        let (spec, extras) = load_spec(&path).unwrap();
+        assert_eq!(extras.metadata["rel"], "v2");
+        assert_eq!(extras.metadata["from"], "bisect");
+        assert_eq!(
+            action_positions(&spec.timeline[0].action_type),
+            vec![[2, 1, 2]]
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use crate::spec::{self, ExtEntry};
+use anyhow::{Context, Result};
+use flint_core::test_spec::TimelineEntry;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::path::Path;
+
+/// Suite-wide actions shared by every test in a run
+///
+/// `setup` runs once before the test areas are cleaned and `teardown` runs once
+/// after the final cleanup. Entries are applied in file order at `offset`, and
+/// their assertions never count toward any test's pass/fail.
+///
+/// `before_each` and `after_each` are instead added to every test's own timeline at
+/// that test's offset: `before_each` ticks count from the test's start and
+/// `after_each` ticks from its last tick. Their assertions count toward the test.
+#[derive(Debug, Default, Deserialize)]
+pub struct Suite {
+    /// World offset applied to every setup/teardown position
+    #[serde(default)]
+    pub offset: [i32; 3],
+    #[serde(default)]
+    pub setup: Vec<TimelineEntry>,
+    #[serde(default)]
+    pub teardown: Vec<TimelineEntry>,
+    #[serde(default, deserialize_with = "hook_entries")]
+    pub before_each: Vec<ExtEntry>,
+    #[serde(default, deserialize_with = "hook_entries")]
+    pub after_each: Vec<ExtEntry>,
+}
+
+/// Parse per-test hook entries like spec timeline entries, extension actions and
+/// relative positions included
+fn hook_entries<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<ExtEntry>, D::Error> {
+    Vec::<Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|mut value| {
+            spec::resolve_relative(&mut value)
+                .and_then(|()| ExtEntry::from_value(value))
+                .map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
+impl Suite {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read suite file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse suite file {}", path.display()))
+    }
+}
@@ -0,0 +1,112 @@
+use crate::blocks::validate_block;
+use crate::spec::{self, EntryAction};
+use flint_core::test_spec::ActionType;
+use flint_core::timeline::TimelineAggregate;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Problems found in one spec file by `flintmc validate`
+#[derive(Debug)]
+pub struct SpecReport {
+    pub path: PathBuf,
+    /// Test name, when the file parsed far enough to have one
+    pub test_name: Option<String>,
+    pub problems: Vec<String>,
+}
+
+impl SpecReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check a spec file without connecting to a server
+/// Covers everything the loader rejects (missing fields, unknown action types),
+/// tick ordering within multi-tick entries, and block ids and states.
+pub fn validate_spec(path: &Path) -> SpecReport {
+    let mut report = SpecReport {
+        path: path.to_path_buf(),
+        test_name: None,
+        problems: Vec::new(),
+    };
+
+    let (spec, extras) = match spec::load_spec(path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            report.problems.push(format!("{:#}", e));
+            return report;
+        }
+    };
+    report.test_name = Some(spec.name.clone());
+
+    if let Ok(value) = spec::load_json(path)
+        && let Some(Value::Array(timeline)) = value.get("timeline")
+    {
+        for (idx, entry) in timeline.iter().enumerate() {
+            report.problems.extend(
+                tick_problems(entry)
+                    .into_iter()
+                    .map(|problem| format!("Timeline entry {}: {}", idx, problem)),
+            );
+        }
+    }
+
+    let aggregate = TimelineAggregate::from_tests(&[(spec, [0, 0, 0])]);
+    for (tick, entries) in &aggregate.timeline {
+        for (_, entry, _) in entries {
+            report.problems.extend(
+                block_problems(&entry.action_type)
+                    .into_iter()
+                    .map(|problem| format!("Tick {}: {}", tick, problem)),
+            );
+        }
+    }
+    for entry in &extras.actions {
+        if let EntryAction::Core(action) = &entry.action {
+            let tick = entry.at.first().copied().unwrap_or_default();
+            report.problems.extend(
+                block_problems(action)
+                    .into_iter()
+                    .map(|problem| format!("Tick {}: {}", tick, problem)),
+            );
+        }
+    }
+
+    report
+}
+
+/// Multi-tick entries must list strictly increasing ticks, one per expected value
+fn tick_problems(entry: &Value) -> Vec<String> {
+    let Some(Value::Array(ticks)) = entry.get("at") else {
+        return Vec::new();
+    };
+
+    let mut problems = Vec::new();
+    if ticks.is_empty() {
+        problems.push("'at' must list at least one tick".to_string());
+    }
+    let ticks: Vec<u64> = ticks.iter().filter_map(Value::as_u64).collect();
+    if ticks.windows(2).any(|pair| pair[0] >= pair[1]) {
+        problems.push(format!("ticks {:?} are not strictly increasing", ticks));
+    }
+    if let Some(Value::Array(values)) = entry.get("values")
+        && values.len() != ticks.len()
+    {
+        problems.push(format!("{} ticks but {} values", ticks.len(), values.len()));
+    }
+    problems
+}
+
+fn block_problems(action: &ActionType) -> Vec<String> {
+    let blocks: Vec<&str> = match action {
+        ActionType::Place { block, .. } => vec![block],
+        ActionType::PlaceEach { blocks } => blocks.iter().map(|p| p.block.as_str()).collect(),
+        ActionType::Fill { with, .. } => vec![with],
+        _ => Vec::new(),
+    };
+    blocks
+        .into_iter()
+        .filter_map(|block| validate_block(block).err())
+        .map(|e| e.to_string())
+        .collect()
+}